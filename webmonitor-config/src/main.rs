@@ -1,8 +1,8 @@
-use std::error::Error;
+use std::{error::Error, future};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    loop {}
+    future::pending::<()>().await;
 
     Ok(())
 }
@@ -0,0 +1,55 @@
+use similar::{ChangeTag, TextDiff};
+
+use crate::model::{DiffUnit, ThresholdDetectorOptions};
+
+use super::ChangeDetect;
+
+/// Treats the contents as changed if the share of changed lines or characters
+/// reaches the given threshold, ignoring smaller changes
+pub struct ThresholdDetector {
+    options: ThresholdDetectorOptions,
+}
+
+impl ThresholdDetector {
+    pub fn with_options(options: ThresholdDetectorOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl ChangeDetect for ThresholdDetector {
+    fn has_changed(&self, prev: &str, new: &str) -> bool {
+        if prev == new {
+            return false;
+        }
+
+        let diff = match self.options.unit {
+            DiffUnit::Lines => TextDiff::from_lines(prev, new),
+            DiffUnit::Chars => TextDiff::from_chars(prev, new),
+        };
+        let changed_percent = (1.0 - diff.ratio() as f64) * 100.0;
+
+        changed_percent >= self.options.min_changed_percent
+    }
+}
+
+/// Only treats the contents as changed if lines were added
+pub struct AdditionsDetector;
+
+impl ChangeDetect for AdditionsDetector {
+    fn has_changed(&self, prev: &str, new: &str) -> bool {
+        TextDiff::from_lines(prev, new)
+            .iter_all_changes()
+            .any(|change| change.tag() == ChangeTag::Insert)
+    }
+}
+
+/// Only treats the contents as changed if lines were removed
+pub struct RemovalsDetector;
+
+impl ChangeDetect for RemovalsDetector {
+    fn has_changed(&self, prev: &str, new: &str) -> bool {
+        TextDiff::from_lines(prev, new)
+            .iter_all_changes()
+            .any(|change| change.tag() == ChangeTag::Delete)
+    }
+}
@@ -0,0 +1,19 @@
+use super::ChangeDetect;
+
+/// Treats any difference between the contents as a change
+pub struct ExactDetector;
+
+impl ChangeDetect for ExactDetector {
+    fn has_changed(&self, prev: &str, new: &str) -> bool {
+        prev != new
+    }
+}
+
+/// Treats the contents as changed if they differ in anything but whitespace
+pub struct IgnoreWhitespaceDetector;
+
+impl ChangeDetect for IgnoreWhitespaceDetector {
+    fn has_changed(&self, prev: &str, new: &str) -> bool {
+        prev.split_whitespace().ne(new.split_whitespace())
+    }
+}
@@ -0,0 +1,9 @@
+mod exact;
+pub use self::exact::*;
+
+mod diff;
+pub use self::diff::*;
+
+pub trait ChangeDetect {
+    fn has_changed(&self, prev: &str, new: &str) -> bool;
+}
@@ -0,0 +1,195 @@
+use similar::{ChangeTag, TextDiff};
+
+use crate::model::{DiffHunk, DiffLine, DiffOptions, DiffTag, Snapshot, SnapshotDiff};
+
+impl From<ChangeTag> for DiffTag {
+    fn from(tag: ChangeTag) -> Self {
+        match tag {
+            ChangeTag::Delete => DiffTag::Delete,
+            ChangeTag::Insert => DiffTag::Insert,
+            ChangeTag::Equal => DiffTag::Equal,
+        }
+    }
+}
+
+impl From<DiffTag> for ChangeTag {
+    fn from(tag: DiffTag) -> Self {
+        match tag {
+            DiffTag::Delete => ChangeTag::Delete,
+            DiffTag::Insert => ChangeTag::Insert,
+            DiffTag::Equal => ChangeTag::Equal,
+        }
+    }
+}
+
+/// Returns the prefix that notifications put in front of a line of a diff
+pub fn line_prefix(tag: ChangeTag) -> &'static str {
+    match tag {
+        ChangeTag::Delete => "- ",
+        ChangeTag::Insert => "+ ",
+        ChangeTag::Equal => "  ",
+    }
+}
+
+/// Renders all lines of both contents, the way notifications show them,
+/// with the same prefixes that diff_snapshots uses for the lines of its hunks
+pub fn render_diff(old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .fold(String::from(""), |mut acc, change| {
+            acc.push_str(line_prefix(change.tag()));
+            acc.push_str(&change.to_string());
+
+            acc
+        })
+}
+
+/// Compares the data of two snapshots line by line, and groups the changed lines into hunks
+/// with the configured number of unchanged lines around them
+pub fn diff_snapshots(old: &Snapshot, new: &Snapshot, options: &DiffOptions) -> SnapshotDiff {
+    let hunks = diff_hunks(old.data.as_str(), new.data.as_str(), options);
+
+    let count = |tag: DiffTag| {
+        hunks
+            .iter()
+            .flat_map(|hunk| hunk.lines.iter())
+            .filter(|line| line.tag == tag)
+            .count()
+    };
+
+    SnapshotDiff {
+        old_snapshot_id: old.id.clone(),
+        new_snapshot_id: new.id.clone(),
+        old_created_at: old.created_at,
+        new_created_at: new.created_at,
+        added: count(DiffTag::Insert),
+        removed: count(DiffTag::Delete),
+        rendered: render_hunks(&hunks),
+        hunks,
+    }
+}
+
+/// Compares both contents line by line, and groups the changed lines into hunks
+fn diff_hunks(old: &str, new: &str, options: &DiffOptions) -> Vec<DiffHunk> {
+    let diff = TextDiff::from_lines(old, new);
+
+    diff.grouped_ops(options.context_lines)
+        .iter()
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            let first = &group[0];
+            let last = &group[group.len() - 1];
+
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    tag: change.tag().into(),
+                    content: change
+                        .value()
+                        .trim_end_matches(&['\r', '\n'][..])
+                        .to_string(),
+                })
+                .collect();
+
+            DiffHunk {
+                old_start: first.old_range().start + 1,
+                old_lines: last.old_range().end - first.old_range().start,
+                new_start: first.new_range().start + 1,
+                new_lines: last.new_range().end - first.new_range().start,
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Renders hunks with a unified diff style header, and their lines
+/// with the same prefixes as notifications
+fn render_hunks(hunks: &[DiffHunk]) -> String {
+    hunks.iter().fold(String::from(""), |mut acc, hunk| {
+        acc.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        ));
+
+        for line in &hunk.lines {
+            acc.push_str(line_prefix(line.tag.into()));
+            acc.push_str(&line.content);
+            acc.push('\n');
+        }
+
+        acc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use mongodb::bson::DateTime;
+
+    use super::*;
+
+    fn snapshot(id: &str, data: String) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            job_id: String::from("job"),
+            data,
+            value: None,
+            raw_data: None,
+            created_at: DateTime::from(Utc.timestamp_millis(0)),
+            fetch: None,
+        }
+    }
+
+    #[test]
+    fn groups_changes_into_hunks_with_context() {
+        let old_lines = (1..=20).map(|i| format!("line {}\n", i));
+        let new_lines = (1..=20).filter(|i| *i != 18).map(|i| match i {
+            5 => String::from("line five\n"),
+            _ => format!("line {}\n", i),
+        });
+        let old = snapshot("old", old_lines.collect());
+        let new = snapshot("new", new_lines.collect());
+        let options = DiffOptions::default();
+
+        let diff = diff_snapshots(&old, &new, &options);
+
+        let ranges: Vec<(usize, usize, usize, usize)> = diff
+            .hunks
+            .iter()
+            .map(|hunk| {
+                (
+                    hunk.old_start,
+                    hunk.old_lines,
+                    hunk.new_start,
+                    hunk.new_lines,
+                )
+            })
+            .collect();
+        assert_eq!(ranges, vec![(2, 7, 2, 7), (15, 6, 15, 5)]);
+        assert_eq!(diff.added, 1);
+        assert_eq!(diff.removed, 2);
+        assert!(diff.rendered.starts_with("@@ -2,7 +2,7 @@\n  line 2\n"));
+        assert!(diff.rendered.contains("- line 5\n+ line five\n"));
+        assert!(diff.rendered.contains("- line 18\n"));
+    }
+
+    #[test]
+    fn equal_contents_have_no_hunks() {
+        let old = snapshot("old", String::from("same\n"));
+        let new = snapshot("new", String::from("same\n"));
+
+        let diff = diff_snapshots(&old, &new, &DiffOptions::default());
+
+        assert!(diff.hunks.is_empty());
+        assert_eq!((diff.added, diff.removed), (0, 0));
+        assert_eq!(diff.rendered, "");
+    }
+
+    #[test]
+    fn renders_all_lines_for_notifications() {
+        let rendered = render_diff("title\nold\nfooter\n", "title\nnew\nfooter\n");
+
+        assert_eq!(rendered, "  title\n- old\n+ new\n  footer\n");
+    }
+}
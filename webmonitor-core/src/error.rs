@@ -15,11 +15,96 @@ pub enum WebmonitorError {
     BsonDeserializeError(#[from] bson::de::Error),
 
     #[error("Error while accessing the Mongodb database")]
-    MongoDBError(#[from] mongodb::error::Error),
+    MongoDBError(#[source] Box<mongodb::error::Error>),
 
     #[error("Error while requesting web data")]
     RequestError(#[from] reqwest::Error),
 
     #[error("Error while parsing the given CSS selector")]
     SelectorParseError,
+
+    #[error("Error while parsing or serializing JSON data")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Error while evaluating the given JSONPath expression: {0}")]
+    JsonPathError(String),
+
+    #[error("Error while parsing the RSS/Atom feed")]
+    FeedParseError(#[from] feed_rs::parser::ParseFeedError),
+
+    #[error("Error while parsing the given regular expression")]
+    RegexParseError(#[from] regex::Error),
+
+    #[error("The given regular expression has no capture group named '{0}'")]
+    UnknownCaptureGroup(String),
+
+    #[error("Error while compiling the given script: {0}")]
+    ScriptCompileError(String),
+
+    #[error("Error while running the given script: {0}")]
+    ScriptRuntimeError(String),
+
+    #[error("Error while extracting text from the PDF document")]
+    PdfError(#[from] lopdf::Error),
+
+    #[error("Error while decoding or encoding an image")]
+    ImageError(#[from] image::ImageError),
+
+    #[error("Couldn't find a number in the filtered content")]
+    NumberNotFound,
+
+    #[error("Error while accessing the SQLite database")]
+    SqliteError(#[from] rusqlite::Error),
+
+    #[error("Error while accessing the Postgres database")]
+    PostgresError(#[from] tokio_postgres::Error),
+
+    #[error("Error while compressing or decompressing snapshot data")]
+    CompressionError(#[from] std::io::Error),
+
+    #[error("The stored data of the snapshot with hash '{0}' is missing")]
+    SnapshotBodyMissing(String),
+
+    #[error("There is no job with the id '{0}'")]
+    JobNotFound(String),
+
+    #[error("The snapshot history cursor '{0}' is invalid")]
+    InvalidCursor(String),
+
+    #[error("There is no snapshot with the id '{0}'")]
+    SnapshotNotFound(String),
+
+    #[error("The job with the id '{0}' has no snapshot at or before the given time")]
+    NoSnapshotAt(String),
+
+    #[error("Invalid script filter limit: {0}")]
+    InvalidScriptLimit(String),
+
+    #[error("Error while running a blocking task")]
+    BlockingTaskError(#[from] tokio::task::JoinError),
+
+    #[error("The mode of the job with the id '{0}' doesn't support this operation")]
+    UnsupportedJobMode(String),
+
+    #[error("Triggers of feed jobs can't be transition only")]
+    TransitionOnlyFeedTrigger,
+
+    #[error("Triggers are only supported by website and feed jobs")]
+    UnsupportedTriggerMode,
+
+    #[error("Please supply a valid {0} in your .env file")]
+    MissingEnvironmentVariable(String),
+
+    #[error("Error while setting up the TLS connection to the database")]
+    TlsError(#[from] native_tls::Error),
+
+    #[error("The snapshots '{0}' and '{1}' belong to different jobs")]
+    SnapshotJobMismatch(String, String),
+}
+
+// Mongodb errors are boxed, as they're much larger than all other errors
+impl From<mongodb::error::Error> for WebmonitorError {
+    fn from(error: mongodb::error::Error) -> Self {
+        WebmonitorError::MongoDBError(Box::new(error))
+    }
 }
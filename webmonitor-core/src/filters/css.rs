@@ -1,9 +1,9 @@
 use async_trait::async_trait;
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 
 use crate::{
     error::{Result, WebmonitorError},
-    model::CSSFilterOptions,
+    model::{CSSFilterOptions, CSSFilterOutput},
 };
 
 use super::FilterApply;
@@ -16,22 +16,147 @@ impl CSSFilter {
     pub fn with_options(options: CSSFilterOptions) -> Self {
         Self { options }
     }
+
+    fn parse_selector(selector: &str) -> Result<Selector> {
+        Selector::parse(selector).map_err(|_| WebmonitorError::SelectorParseError)
+    }
+
+    fn extract(&self, elem: ElementRef) -> Option<String> {
+        if let Some(attribute) = &self.options.attribute {
+            return elem.value().attr(attribute.as_str()).map(String::from);
+        }
+
+        let result = match self.options.output {
+            CSSFilterOutput::Html => elem.html(),
+            CSSFilterOutput::InnerHtml => elem.inner_html(),
+            CSSFilterOutput::Text => elem.text().collect(),
+        };
+
+        Some(result)
+    }
 }
 
 #[async_trait]
 impl FilterApply for CSSFilter {
     fn apply(&self, dom: String) -> Result<String> {
-        let fragment = Html::parse_fragment(dom.as_str());
-        let selector = Selector::parse(self.options.selector.as_str())
-            .map_err(|e| WebmonitorError::SelectorParseError)?;
+        let mut fragment = Html::parse_fragment(dom.as_str());
+        let selector = Self::parse_selector(self.options.selector.as_str())?;
+
+        if let Some(exclude_selector) = &self.options.exclude_selector {
+            let exclude_selector = Self::parse_selector(exclude_selector.as_str())?;
+            let excluded_ids: Vec<_> = fragment
+                .select(&exclude_selector)
+                .map(|elem| elem.id())
+                .collect();
+
+            for id in excluded_ids {
+                if let Some(mut node) = fragment.tree.get_mut(id) {
+                    node.detach();
+                }
+            }
+        }
+
+        let elements: Vec<ElementRef> = match self.options.nth {
+            Some(n) => fragment.select(&selector).nth(n).into_iter().collect(),
+            None => fragment.select(&selector).collect(),
+        };
 
-        let result = fragment
-            .select(&selector)
-            .fold(String::from(""), |mut acc, elem| {
-                acc.push_str(elem.html().as_str());
-                acc
-            });
+        let result = elements
+            .into_iter()
+            .filter_map(|elem| self.extract(elem))
+            .collect::<Vec<String>>()
+            .join(self.options.separator.as_str());
 
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"<ul>
+        <li class="item"><a href="/a">First <b>one</b></a></li>
+        <li class="item ad"><a href="/ad">Advertisement</a></li>
+        <li class="item"><a>Second</a></li>
+    </ul>"#;
+
+    fn options(selector: &str) -> CSSFilterOptions {
+        CSSFilterOptions {
+            selector: selector.to_string(),
+            output: CSSFilterOutput::default(),
+            attribute: None,
+            exclude_selector: None,
+            nth: None,
+            separator: String::new(),
+        }
+    }
+
+    fn apply(options: CSSFilterOptions) -> Result<String> {
+        CSSFilter::with_options(options).apply(HTML.to_string())
+    }
+
+    #[test]
+    fn outputs_the_html_of_matches_by_default() {
+        let expected = "<b>one</b>";
+
+        assert_eq!(apply(options("li b")).unwrap(), expected);
+    }
+
+    #[test]
+    fn outputs_inner_html_or_text() {
+        let mut inner_html = options("li.item:first-child a");
+        inner_html.output = CSSFilterOutput::InnerHtml;
+        let mut text = options("li.item:first-child a");
+        text.output = CSSFilterOutput::Text;
+
+        assert_eq!(apply(inner_html).unwrap(), "First <b>one</b>");
+        assert_eq!(apply(text).unwrap(), "First one");
+    }
+
+    #[test]
+    fn outputs_attributes_and_skips_elements_without_them() {
+        let mut options = options("a");
+        options.attribute = Some(String::from("href"));
+        options.separator = String::from(",");
+
+        assert_eq!(apply(options).unwrap(), "/a,/ad");
+    }
+
+    #[test]
+    fn removes_excluded_elements_before_selecting() {
+        let mut options = options("li");
+        options.output = CSSFilterOutput::Text;
+        options.exclude_selector = Some(String::from(".ad"));
+        options.separator = String::from("\n");
+
+        assert_eq!(apply(options).unwrap(), "First one\nSecond");
+    }
+
+    #[test]
+    fn outputs_only_the_nth_match() {
+        let mut second = options("a");
+        second.output = CSSFilterOutput::Text;
+        second.nth = Some(1);
+        let mut missing = second.clone();
+        missing.nth = Some(3);
+
+        assert_eq!(apply(second).unwrap(), "Advertisement");
+        assert_eq!(apply(missing).unwrap(), "");
+    }
+
+    #[test]
+    fn rejects_invalid_selectors() {
+        let mut exclude = options("li");
+        exclude.exclude_selector = Some(String::from("li["));
+
+        assert!(matches!(
+            apply(options("li[")),
+            Err(WebmonitorError::SelectorParseError)
+        ));
+        assert!(matches!(
+            apply(exclude),
+            Err(WebmonitorError::SelectorParseError)
+        ));
+    }
+}
@@ -1,18 +1,329 @@
 use async_trait::async_trait;
-use scraper::Html;
+use scraper::{ElementRef, Html, Node};
 
 use crate::error::Result;
 
 use super::FilterApply;
 
+/// Converts HTML to readable plain text, putting block elements, list items
+/// and table rows on their own lines and appending link targets in brackets.
 pub struct Html2TextFilter;
 
 #[async_trait]
 impl FilterApply for Html2TextFilter {
     fn apply(&self, dom: String) -> Result<String> {
-        let fragment = Html::parse_fragment(dom.as_str());
-        let result = fragment.root_element().text().collect();
+        Ok(HtmlRenderer { markdown: false }.render(dom.as_str()))
+    }
+}
+
+/// Converts HTML to Markdown, with the same structure as the Html2TextFilter
+/// but keeping headings, emphasis, links and images as Markdown markup.
+pub struct Html2MarkdownFilter;
+
+#[async_trait]
+impl FilterApply for Html2MarkdownFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        Ok(HtmlRenderer { markdown: true }.render(dom.as_str()))
+    }
+}
+
+const SKIPPED_ELEMENTS: &[&str] = &["head", "title", "script", "style", "noscript", "template"];
+
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "main",
+    "nav",
+    "p",
+    "pre",
+    "section",
+    "table",
+];
+
+struct HtmlRenderer {
+    markdown: bool,
+}
+
+impl HtmlRenderer {
+    fn render(&self, dom: &str) -> String {
+        let fragment = Html::parse_fragment(dom);
+        let mut out = String::new();
+
+        self.render_children(fragment.root_element(), &mut out, false);
+
+        tidy_lines(&out)
+    }
+
+    fn render_children(&self, elem: ElementRef, out: &mut String, preformatted: bool) {
+        for child in elem.children() {
+            match child.value() {
+                Node::Text(text) => push_text(out, text, preformatted),
+                Node::Element(_) => {
+                    if let Some(child_elem) = ElementRef::wrap(child) {
+                        self.render_element(child_elem, out, preformatted);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn render_element(&self, elem: ElementRef, out: &mut String, preformatted: bool) {
+        let name = elem.value().name();
+
+        if SKIPPED_ELEMENTS.contains(&name) {
+            return;
+        }
+
+        match name {
+            "br" => out.push('\n'),
+            "hr" => {
+                push_newline(out);
+                out.push_str("---\n");
+            }
+            "ul" | "ol" => {
+                self.push_block_break(out);
+
+                let items = elem
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|child| child.value().name() == "li");
+
+                for (index, item) in items.enumerate() {
+                    push_newline(out);
+                    if name == "ol" {
+                        out.push_str(format!("{}. ", index + 1).as_str());
+                    } else {
+                        out.push_str("- ");
+                    }
+                    self.render_children(item, out, preformatted);
+                }
+
+                self.push_block_break(out);
+            }
+            "li" => {
+                push_newline(out);
+                out.push_str("- ");
+                self.render_children(elem, out, preformatted);
+                push_newline(out);
+            }
+            "tr" => {
+                push_newline(out);
+                self.render_children(elem, out, preformatted);
+                push_newline(out);
+            }
+            "td" | "th" => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push_str(" | ");
+                }
+                self.render_children(elem, out, preformatted);
+            }
+            "a" => self.render_link(elem, out, preformatted),
+            "img" if self.markdown => {
+                if let Some(src) = elem.value().attr("src") {
+                    let alt = elem.value().attr("alt").unwrap_or("");
+                    out.push_str(format!("![{}]({})", alt, src).as_str());
+                }
+            }
+            "strong" | "b" if self.markdown => {
+                out.push_str("**");
+                self.render_children(elem, out, preformatted);
+                out.push_str("**");
+            }
+            "em" | "i" if self.markdown => {
+                out.push('*');
+                self.render_children(elem, out, preformatted);
+                out.push('*');
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => {
+                self.push_block_break(out);
+                if self.markdown {
+                    if let Some(level) = heading_level(name) {
+                        out.push_str(format!("{} ", "#".repeat(level)).as_str());
+                    }
+                }
+                self.render_children(elem, out, preformatted || name == "pre");
+                self.push_block_break(out);
+            }
+            _ => self.render_children(elem, out, preformatted),
+        }
+    }
+
+    /// Ends the current line, and separates blocks by an empty line in Markdown
+    fn push_block_break(&self, out: &mut String) {
+        push_newline(out);
+
+        if self.markdown && !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+    }
+
+    fn render_link(&self, elem: ElementRef, out: &mut String, preformatted: bool) {
+        let href = elem.value().attr("href").unwrap_or("");
+
+        if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+            self.render_children(elem, out, preformatted);
+            return;
+        }
+
+        if self.markdown {
+            out.push('[');
+            self.render_children(elem, out, preformatted);
+            out.push_str(format!("]({})", href).as_str());
+        } else {
+            let start = out.len();
+            self.render_children(elem, out, preformatted);
+
+            if out[start..].trim() != href {
+                out.push_str(format!(" [{}]", href).as_str());
+            }
+        }
+    }
+}
+
+fn heading_level(name: &str) -> Option<usize> {
+    match name {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// Appends a text node, collapsing its whitespace unless it's preformatted
+fn push_text(out: &mut String, text: &str, preformatted: bool) {
+    if preformatted {
+        out.push_str(text);
+        return;
+    }
+
+    let starts_with_space = text.starts_with(char::is_whitespace);
+    let ends_with_space = text.ends_with(char::is_whitespace);
+    let words = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    let at_line_start = out.is_empty() || out.ends_with('\n');
+    if starts_with_space && !at_line_start && !out.ends_with(' ') {
+        out.push(' ');
+    }
+
+    out.push_str(words.as_str());
+
+    if ends_with_space && !words.is_empty() {
+        out.push(' ');
+    }
+}
+
+fn push_newline(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Removes trailing whitespace of every line and collapses runs of blank lines into one
+fn tidy_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in text.lines().map(str::trim_end) {
+        let prev_blank = lines.last().is_none_or(|prev| prev.is_empty());
+        if !(line.is_empty() && prev_blank) {
+            lines.push(line);
+        }
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r##"<html><head><title>Shop</title><style>p { color: red }</style></head>
+        <body>
+            <h1>Offers</h1>
+            <p>Prices   are <b>low</b>,<br>see <a href="/deals">all deals</a>.</p>
+            <ul><li>Apple</li><li><i>Pear</i></li></ul>
+            <ol><li>First</li><li>Second</li></ol>
+            <table><tr><th>Name</th><th>Price</th></tr><tr><td>Plum</td><td>2 EUR</td></tr></table>
+            <pre>  keep
+    spacing</pre>
+            <p><a href="https://example.com">https://example.com</a> <a href="#top">Top</a>
+            <img src="/logo.png" alt="Logo"></p>
+            <script>alert("hidden")</script>
+        </body></html>"##;
+
+    #[test]
+    fn renders_blocks_lists_and_tables_on_their_own_lines() {
+        let expected = "Offers
+Prices are low,
+see all deals [/deals].
+- Apple
+- Pear
+1. First
+2. Second
+Name | Price
+Plum | 2 EUR
+  keep
+    spacing
+https://example.com Top";
+
+        assert_eq!(Html2TextFilter.apply(HTML.to_string()).unwrap(), expected);
+    }
+
+    #[test]
+    fn renders_markdown_markup() {
+        let expected = "# Offers
+
+Prices are **low**,
+see [all deals](/deals).
+
+- Apple
+- *Pear*
+
+1. First
+2. Second
+
+Name | Price
+Plum | 2 EUR
+
+  keep
+    spacing
+
+[https://example.com](https://example.com) Top ![Logo](/logo.png)";
+
+        assert_eq!(
+            Html2MarkdownFilter.apply(HTML.to_string()).unwrap(),
+            expected
+        );
+    }
 
-        Ok(result)
+    #[test]
+    fn renders_plain_text_as_is() {
+        assert_eq!(
+            Html2TextFilter
+                .apply(String::from("  just  text "))
+                .unwrap(),
+            "just text"
+        );
     }
 }
@@ -0,0 +1,109 @@
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use crate::{
+    error::{Result, WebmonitorError},
+    model::JsonPathFilterOptions,
+};
+
+use super::FilterApply;
+
+pub struct JsonPathFilter {
+    options: JsonPathFilterOptions,
+}
+
+impl JsonPathFilter {
+    pub fn with_options(options: JsonPathFilterOptions) -> Self {
+        Self { options }
+    }
+}
+
+#[async_trait]
+impl FilterApply for JsonPathFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let json: Value = serde_json::from_str(dom.as_str())?;
+        let selected = jsonpath_lib::select(&json, self.options.selector.as_str())
+            .map_err(|e| WebmonitorError::JsonPathError(e.to_string()))?;
+
+        // Matches are always emitted as an array, so the output's shape doesn't depend
+        // on how many elements the selector happens to match
+        let result = Value::Array(selected.into_iter().map(sort_keys).collect());
+
+        Ok(serde_json::to_string_pretty(&result)?)
+    }
+}
+
+/// Returns a copy of the given value with all object keys sorted,
+/// so that the serialized output stays stable between checks.
+fn sort_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<(&String, &Value)> = object.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), sort_keys(value)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.iter().map(sort_keys).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{
+        "store": {
+            "name": "Shop",
+            "items": [
+                { "price": 10, "name": "Apple", "tags": { "z": 1, "a": 2 } },
+                { "price": 20, "name": "Pear" }
+            ]
+        }
+    }"#;
+
+    fn apply(selector: &str, json: &str) -> Result<String> {
+        JsonPathFilter::with_options(JsonPathFilterOptions {
+            selector: selector.to_string(),
+        })
+        .apply(json.to_string())
+    }
+
+    #[test]
+    fn emits_a_single_match_as_an_array() {
+        assert_eq!(apply("$.store.name", JSON).unwrap(), "[\n  \"Shop\"\n]");
+    }
+
+    #[test]
+    fn emits_multiple_matches_as_an_array() {
+        let expected = "[\n  10,\n  20\n]";
+
+        assert_eq!(apply("$.store.items[*].price", JSON).unwrap(), expected);
+    }
+
+    #[test]
+    fn sorts_object_keys_at_every_level() {
+        let expected = "[\n  {\n    \"name\": \"Apple\",\n    \"price\": 10,\n    \"tags\": {\n      \"a\": 2,\n      \"z\": 1\n    }\n  }\n]";
+
+        assert_eq!(apply("$.store.items[0]", JSON).unwrap(), expected);
+    }
+
+    #[test]
+    fn emits_an_empty_array_without_matches() {
+        assert_eq!(apply("$.store.missing", JSON).unwrap(), "[]");
+    }
+
+    #[test]
+    fn rejects_invalid_json_and_selectors() {
+        assert!(apply("$.store", "not json").is_err());
+        assert!(matches!(
+            apply("$.[", JSON),
+            Err(WebmonitorError::JsonPathError(_))
+        ));
+    }
+}
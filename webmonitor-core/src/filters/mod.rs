@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 
-use crate::error::Result;
+use crate::{error::Result, model::Filter};
 
 mod css;
 pub use self::css::*;
@@ -11,7 +11,43 @@ pub use self::xpath::*;
 mod html2text;
 pub use self::html2text::*;
 
+mod jsonpath;
+pub use self::jsonpath::*;
+
+mod regex;
+pub use self::regex::*;
+
+mod text;
+pub use self::text::*;
+
+mod script;
+pub use self::script::*;
+
+mod pdf;
+pub use self::pdf::*;
+
 #[async_trait]
 pub trait FilterApply {
     fn apply(&self, dom: String) -> Result<String>;
 }
+
+/// Checks that all of the given filters can be built from their options,
+/// so that invalid jobs are rejected when they're added instead of on every check.
+pub fn validate_filters(filters: &[Filter]) -> Result<()> {
+    for filter in filters {
+        match filter {
+            Filter::RegexExtractFilter(options) => {
+                RegexExtractFilter::with_options(options.clone())?;
+            }
+            Filter::RegexReplaceFilter(options) => {
+                RegexReplaceFilter::with_options(options.clone())?;
+            }
+            Filter::ScriptFilter(options) => {
+                ScriptFilter::with_options(options.clone())?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
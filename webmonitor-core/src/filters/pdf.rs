@@ -0,0 +1,41 @@
+use lopdf::Document;
+use reqwest::{header::CONTENT_TYPE, Response};
+
+use crate::error::Result;
+
+/// Extracts the text of every page of a PDF document, so that the other filters
+/// and the notifiers' diffs work on readable text instead of the binary document.
+/// Unlike the other filters it works on the raw response body, and is applied
+/// automatically to every response that is detected to be a PDF.
+pub struct PdfTextFilter;
+
+impl PdfTextFilter {
+    /// Returns whether the response is a PDF document, either by its content type or,
+    /// for servers that only send a generic binary content type, by its file extension.
+    pub fn is_pdf_response(response: &Response) -> bool {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+
+        content_type.starts_with("application/pdf")
+            || (content_type.starts_with("application/octet-stream")
+                && response.url().path().to_lowercase().ends_with(".pdf"))
+    }
+
+    pub fn extract(&self, body: &[u8]) -> Result<String> {
+        let document = Document::load_mem(body)?;
+
+        let pages = document
+            .get_pages()
+            .keys()
+            .map(|page_number| -> Result<String> {
+                let text = document.extract_text(&[*page_number])?;
+                Ok(format!("--- Page {} ---\n{}", page_number, text.trim()))
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        Ok(pages.join("\n\n"))
+    }
+}
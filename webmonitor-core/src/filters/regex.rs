@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use regex::Regex;
+
+use crate::{
+    error::{Result, WebmonitorError},
+    model::{RegexExtractFilterOptions, RegexReplaceFilterOptions},
+};
+
+use super::FilterApply;
+
+pub struct RegexExtractFilter {
+    regex: Regex,
+    group: Option<String>,
+}
+
+impl RegexExtractFilter {
+    /// Compiles the filter's pattern, failing if it isn't a valid regular expression
+    /// or doesn't contain the capture group that should be extracted.
+    pub fn with_options(options: RegexExtractFilterOptions) -> Result<Self> {
+        let regex = Regex::new(options.pattern.as_str())?;
+
+        if let Some(group) = &options.group {
+            if !regex
+                .capture_names()
+                .any(|name| name == Some(group.as_str()))
+            {
+                return Err(WebmonitorError::UnknownCaptureGroup(group.clone()));
+            }
+        }
+
+        Ok(Self {
+            regex,
+            group: options.group,
+        })
+    }
+}
+
+#[async_trait]
+impl FilterApply for RegexExtractFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let matches: Vec<&str> = match &self.group {
+            Some(group) => self
+                .regex
+                .captures_iter(dom.as_str())
+                .filter_map(|captures| captures.name(group.as_str()))
+                .map(|capture| capture.as_str())
+                .collect(),
+            None => self
+                .regex
+                .find_iter(dom.as_str())
+                .map(|found| found.as_str())
+                .collect(),
+        };
+
+        Ok(matches.join("\n"))
+    }
+}
+
+pub struct RegexReplaceFilter {
+    regex: Regex,
+    replacement: String,
+}
+
+impl RegexReplaceFilter {
+    /// Compiles the filter's pattern, failing if it isn't a valid regular expression.
+    pub fn with_options(options: RegexReplaceFilterOptions) -> Result<Self> {
+        Ok(Self {
+            regex: Regex::new(options.pattern.as_str())?,
+            replacement: options.replacement,
+        })
+    }
+}
+
+#[async_trait]
+impl FilterApply for RegexReplaceFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let result = self
+            .regex
+            .replace_all(dom.as_str(), self.replacement.as_str());
+
+        Ok(result.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(pattern: &str, group: Option<&str>) -> Result<RegexExtractFilter> {
+        RegexExtractFilter::with_options(RegexExtractFilterOptions {
+            pattern: pattern.to_string(),
+            group: group.map(String::from),
+        })
+    }
+
+    fn replace(pattern: &str, replacement: &str) -> Result<RegexReplaceFilter> {
+        RegexReplaceFilter::with_options(RegexReplaceFilterOptions {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+        })
+    }
+
+    const TEXT: &str = "Apple: 1.20 EUR\nPear: 0.80 EUR\nPlum: sold out";
+
+    #[test]
+    fn extracts_every_match_on_its_own_line() {
+        let filter = extract(r"\d+\.\d+", None).unwrap();
+
+        assert_eq!(filter.apply(TEXT.to_string()).unwrap(), "1.20\n0.80");
+    }
+
+    #[test]
+    fn extracts_named_groups_of_matches() {
+        let filter = extract(r"(?m)^(?P<name>\w+): (?P<price>\d+\.\d+)", Some("name")).unwrap();
+
+        assert_eq!(filter.apply(TEXT.to_string()).unwrap(), "Apple\nPear");
+    }
+
+    #[test]
+    fn extracts_nothing_without_matches() {
+        let filter = extract("banana", None).unwrap();
+
+        assert_eq!(filter.apply(TEXT.to_string()).unwrap(), "");
+    }
+
+    #[test]
+    fn rejects_invalid_patterns_and_unknown_groups() {
+        assert!(matches!(
+            extract("(unclosed", None),
+            Err(WebmonitorError::RegexParseError(_))
+        ));
+        assert!(matches!(
+            extract(r"(?P<price>\d+)", Some("name")),
+            Err(WebmonitorError::UnknownCaptureGroup(group)) if group == "name"
+        ));
+        assert!(matches!(
+            replace("[", ""),
+            Err(WebmonitorError::RegexParseError(_))
+        ));
+    }
+
+    #[test]
+    fn replaces_every_match_with_group_references() {
+        let filter = replace(r"(\d+)\.(\d+) EUR", "$1,$2 €").unwrap();
+
+        let expected = "Apple: 1,20 €\nPear: 0,80 €\nPlum: sold out";
+        assert_eq!(filter.apply(TEXT.to_string()).unwrap(), expected);
+    }
+}
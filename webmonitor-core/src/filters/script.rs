@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use rhai::{Dynamic, Engine, Scope, AST};
+
+use crate::{
+    error::{Result, WebmonitorError},
+    model::ScriptFilterOptions,
+};
+
+use super::FilterApply;
+
+const DEFAULT_MAX_OPERATIONS: u64 = 1_000_000;
+const DEFAULT_TIMEOUT_MILLIS: u64 = 1_000;
+
+// Upper bounds for the limits a job can configure
+const MAX_OPERATIONS: u64 = 100_000_000;
+const MAX_TIMEOUT_MILLIS: u64 = 30_000;
+
+// Limits on the size of values a script can create, to bound its memory usage
+const MAX_STRING_SIZE: usize = 16 * 1024 * 1024;
+const MAX_COLLECTION_SIZE: usize = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+
+/// Runs a Rhai script over the content. The content is available to the script
+/// as the `input` variable, and the value of the script's last expression becomes the output.
+/// Scripts have no access to the file system or network, and are aborted
+/// once they exceed their operation or time limit.
+/// Scripts can be slow, so filter chains should be applied off the async runtime.
+pub struct ScriptFilter {
+    options: ScriptFilterOptions,
+    ast: AST,
+}
+
+impl ScriptFilter {
+    /// Compiles the filter's script, failing if it contains syntax errors
+    /// or one of its limits is 0, which would disable the limit.
+    pub fn with_options(options: ScriptFilterOptions) -> Result<Self> {
+        if options.max_operations == Some(0) {
+            return Err(WebmonitorError::InvalidScriptLimit(String::from(
+                "max_operations must be greater than 0",
+            )));
+        }
+        if options.timeout_millis == Some(0) {
+            return Err(WebmonitorError::InvalidScriptLimit(String::from(
+                "timeout_millis must be greater than 0",
+            )));
+        }
+
+        let ast = Engine::new()
+            .compile(options.script.as_str())
+            .map_err(|e| WebmonitorError::ScriptCompileError(e.to_string()))?;
+
+        Ok(Self { options, ast })
+    }
+}
+
+#[async_trait]
+impl FilterApply for ScriptFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let max_operations = self
+            .options
+            .max_operations
+            .unwrap_or(DEFAULT_MAX_OPERATIONS)
+            .min(MAX_OPERATIONS);
+        let timeout = Duration::from_millis(
+            self.options
+                .timeout_millis
+                .unwrap_or(DEFAULT_TIMEOUT_MILLIS)
+                .min(MAX_TIMEOUT_MILLIS),
+        );
+        let started = Instant::now();
+
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(max_operations)
+            .set_max_string_size(MAX_STRING_SIZE)
+            .set_max_array_size(MAX_COLLECTION_SIZE)
+            .set_max_map_size(MAX_COLLECTION_SIZE)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .on_print(|_| {})
+            .on_debug(|_, _, _| {})
+            .on_progress(move |_| {
+                if started.elapsed() > timeout {
+                    Some(Dynamic::from("Script exceeded its time limit"))
+                } else {
+                    None
+                }
+            });
+
+        let mut scope = Scope::new();
+        scope.push("input", dom);
+
+        let result: Dynamic = engine
+            .eval_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| WebmonitorError::ScriptRuntimeError(e.to_string()))?;
+
+        Ok(result.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(
+        script: &str,
+        max_operations: Option<u64>,
+        timeout_millis: Option<u64>,
+    ) -> Result<ScriptFilter> {
+        ScriptFilter::with_options(ScriptFilterOptions {
+            script: script.to_string(),
+            max_operations,
+            timeout_millis,
+        })
+    }
+
+    #[test]
+    fn outputs_the_value_of_the_last_expression() {
+        let filter = script(r#"input.to_upper() + "!""#, None, None).unwrap();
+
+        assert_eq!(
+            filter.apply(String::from("price: 12")).unwrap(),
+            "PRICE: 12!"
+        );
+    }
+
+    #[test]
+    fn rejects_scripts_with_syntax_errors() {
+        assert!(matches!(
+            script("let x = ;", None, None),
+            Err(WebmonitorError::ScriptCompileError(_))
+        ));
+    }
+
+    #[test]
+    fn aborts_scripts_exceeding_their_operation_limit() {
+        let filter = script("loop {}", Some(1_000), None).unwrap();
+
+        assert!(matches!(
+            filter.apply(String::new()),
+            Err(WebmonitorError::ScriptRuntimeError(_))
+        ));
+    }
+
+    #[test]
+    fn aborts_scripts_exceeding_their_time_limit() {
+        let filter = script("loop {}", Some(u64::MAX), Some(50)).unwrap();
+        let started = Instant::now();
+
+        let result = filter.apply(String::new());
+
+        assert!(matches!(
+            result,
+            Err(WebmonitorError::ScriptRuntimeError(_))
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn bounds_the_size_of_created_values() {
+        let filter = script(r#"let s = "x"; loop { s += s; }"#, None, Some(5_000)).unwrap();
+
+        assert!(matches!(
+            filter.apply(String::new()),
+            Err(WebmonitorError::ScriptRuntimeError(_))
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    error::Result,
+    model::{UnicodeNormalizationForm, UnicodeNormalizeFilterOptions},
+};
+
+use super::FilterApply;
+
+/// Removes leading and trailing whitespace of every line
+pub struct TrimLinesFilter;
+
+#[async_trait]
+impl FilterApply for TrimLinesFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let result = dom.lines().map(str::trim).collect::<Vec<&str>>().join("\n");
+
+        Ok(result)
+    }
+}
+
+/// Replaces every run of whitespace inside a line with a single space,
+/// and removes leading and trailing whitespace of every line
+pub struct CollapseWhitespaceFilter;
+
+#[async_trait]
+impl FilterApply for CollapseWhitespaceFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let result = dom
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(result)
+    }
+}
+
+/// Removes all lines that are empty or only contain whitespace
+pub struct RemoveBlankLinesFilter;
+
+#[async_trait]
+impl FilterApply for RemoveBlankLinesFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let result = dom
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        Ok(result)
+    }
+}
+
+/// Sorts all lines alphabetically, so that reordered lists don't count as changes
+pub struct SortLinesFilter;
+
+#[async_trait]
+impl FilterApply for SortLinesFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let mut lines: Vec<&str> = dom.lines().collect();
+        lines.sort_unstable();
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Removes repeated lines, keeping only the first occurrence of each
+pub struct DeduplicateLinesFilter;
+
+#[async_trait]
+impl FilterApply for DeduplicateLinesFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let mut seen_lines = HashSet::new();
+        let result = dom
+            .lines()
+            .filter(|line| seen_lines.insert(*line))
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        Ok(result)
+    }
+}
+
+/// Converts the whole text to lowercase
+pub struct CaseFoldFilter;
+
+#[async_trait]
+impl FilterApply for CaseFoldFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        Ok(dom.to_lowercase())
+    }
+}
+
+/// Converts the text to the given Unicode normalization form, so that
+/// differently encoded but equivalent characters compare as equal
+pub struct UnicodeNormalizeFilter {
+    options: UnicodeNormalizeFilterOptions,
+}
+
+impl UnicodeNormalizeFilter {
+    pub fn with_options(options: UnicodeNormalizeFilterOptions) -> Self {
+        Self { options }
+    }
+}
+
+#[async_trait]
+impl FilterApply for UnicodeNormalizeFilter {
+    fn apply(&self, dom: String) -> Result<String> {
+        let result = match self.options.form {
+            UnicodeNormalizationForm::Nfc => dom.nfc().collect(),
+            UnicodeNormalizationForm::Nfd => dom.nfd().collect(),
+            UnicodeNormalizationForm::Nfkc => dom.nfkc().collect(),
+            UnicodeNormalizationForm::Nfkd => dom.nfkd().collect(),
+        };
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(filter: impl FilterApply, text: &str) -> String {
+        filter.apply(text.to_string()).unwrap()
+    }
+
+    #[test]
+    fn trims_every_line() {
+        assert_eq!(apply(TrimLinesFilter, "  a  \r\n\tb\n c"), "a\nb\nc");
+    }
+
+    #[test]
+    fn collapses_whitespace_inside_lines() {
+        let text = "  Price:\t 12  EUR \n\u{a0}in  stock";
+
+        assert_eq!(
+            apply(CollapseWhitespaceFilter, text),
+            "Price: 12 EUR\nin stock"
+        );
+    }
+
+    #[test]
+    fn removes_blank_lines() {
+        assert_eq!(apply(RemoveBlankLinesFilter, "a\n\n \t\nb\n"), "a\nb");
+    }
+
+    #[test]
+    fn sorts_lines() {
+        assert_eq!(
+            apply(SortLinesFilter, "pear\napple\nPlum"),
+            "Plum\napple\npear"
+        );
+    }
+
+    #[test]
+    fn keeps_the_first_of_repeated_lines() {
+        assert_eq!(apply(DeduplicateLinesFilter, "b\na\nb\nc\na"), "b\na\nc");
+    }
+
+    #[test]
+    fn folds_case_beyond_ascii() {
+        assert_eq!(
+            apply(CaseFoldFilter, "ÄPFEL Und BIRNEN"),
+            "äpfel und birnen"
+        );
+    }
+
+    #[test]
+    fn normalizes_to_the_given_form() {
+        let normalize =
+            |form| UnicodeNormalizeFilter::with_options(UnicodeNormalizeFilterOptions { form });
+        let composed = "\u{e9}";
+        let decomposed = "e\u{301}";
+
+        assert_eq!(
+            apply(normalize(UnicodeNormalizationForm::Nfc), decomposed),
+            composed
+        );
+        assert_eq!(
+            apply(normalize(UnicodeNormalizationForm::Nfd), composed),
+            decomposed
+        );
+        assert_eq!(
+            apply(normalize(UnicodeNormalizationForm::Nfkc), "ﬁ½"),
+            "fi1⁄2"
+        );
+        assert_eq!(apply(normalize(UnicodeNormalizationForm::Nfkd), "Ⅸ"), "IX");
+    }
+}
@@ -5,6 +5,7 @@ use crate::{error::Result, model::XPathFilterOptions};
 use super::FilterApply;
 
 pub struct XPathFilter {
+    #[allow(dead_code)]
     options: XPathFilterOptions,
 }
 
@@ -0,0 +1,153 @@
+use image::{
+    imageops::FilterType, DynamicImage, GenericImageView, GrayImage, ImageOutputFormat, Luma, Rgb,
+    RgbImage,
+};
+
+use crate::error::Result;
+
+const THUMBNAIL_SIZE: u32 = 64;
+
+/// The number of differing perceptual hash bits an image needs to count as changed,
+/// unless the job configures its own
+pub const DEFAULT_MIN_PERCEPTUAL_DISTANCE: u32 = 1;
+
+// How much the brightness of a thumbnail pixel may change before it counts as changed
+const PIXEL_TOLERANCE: u8 = 16;
+
+/// A compact representation of an image that's stored in the snapshots of image jobs,
+/// consisting of a perceptual hash (dHash), the original dimensions
+/// and a small grayscale thumbnail used to compute the share of changed pixels.
+pub struct ImageFingerprint {
+    pub dhash: u64,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail: GrayImage,
+}
+
+impl ImageFingerprint {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let hash_image = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+        let mut dhash: u64 = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                dhash <<= 1;
+                if hash_image.get_pixel(x, y)[0] < hash_image.get_pixel(x + 1, y)[0] {
+                    dhash |= 1;
+                }
+            }
+        }
+
+        Self {
+            dhash,
+            width: image.width(),
+            height: image.height(),
+            thumbnail: image
+                .resize_exact(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle)
+                .to_luma8(),
+        }
+    }
+
+    /// Parses a fingerprint back from the data of a snapshot,
+    /// returning None if the snapshot wasn't created by an image job.
+    pub fn from_summary(summary: &str) -> Option<Self> {
+        let field = |name: &str| {
+            summary
+                .lines()
+                .find_map(|line| line.strip_prefix(format!("{}: ", name).as_str()))
+        };
+
+        let dhash = u64::from_str_radix(field("dhash")?, 16).ok()?;
+
+        let mut dimensions = field("dimensions")?.split('x');
+        let width = dimensions.next()?.parse().ok()?;
+        let height = dimensions.next()?.parse().ok()?;
+
+        let thumbnail_data = base64::decode(field("thumbnail")?).ok()?;
+        let thumbnail = GrayImage::from_raw(THUMBNAIL_SIZE, THUMBNAIL_SIZE, thumbnail_data)?;
+
+        Some(Self {
+            dhash,
+            width,
+            height,
+            thumbnail,
+        })
+    }
+
+    /// Serializes the fingerprint to be stored as a snapshot's data,
+    /// along with how much it differs from the previous image, if there was one.
+    pub fn to_summary(&self, prev: Option<&ImageFingerprint>) -> String {
+        let mut lines = vec![
+            format!("dhash: {:016x}", self.dhash),
+            format!("dimensions: {}x{}", self.width, self.height),
+        ];
+
+        if let Some(prev) = prev {
+            lines.push(format!(
+                "perceptual distance: {}/64",
+                self.perceptual_distance(prev)
+            ));
+            lines.push(format!(
+                "changed pixels: {:.1}%",
+                self.difference_percent(prev)
+            ));
+        }
+
+        lines.push(format!(
+            "thumbnail: {}",
+            base64::encode(self.thumbnail.as_raw())
+        ));
+
+        lines.join("\n")
+    }
+
+    /// Returns the human readable lines of a summary, leaving out the thumbnail data
+    pub fn describe_summary(summary: &str) -> String {
+        summary
+            .lines()
+            .filter(|line| !line.starts_with("thumbnail: "))
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    /// Number of bits in which the perceptual hashes differ, from 0 (same) to 64
+    pub fn perceptual_distance(&self, other: &ImageFingerprint) -> u32 {
+        (self.dhash ^ other.dhash).count_ones()
+    }
+
+    /// Share of thumbnail pixels that changed noticeably, in percent
+    pub fn difference_percent(&self, other: &ImageFingerprint) -> f64 {
+        let changed = self
+            .thumbnail
+            .pixels()
+            .zip(other.thumbnail.pixels())
+            .filter(|(a, b)| pixel_changed(a, b))
+            .count();
+
+        changed as f64 * 100.0 / (THUMBNAIL_SIZE * THUMBNAIL_SIZE) as f64
+    }
+
+    /// Renders the thumbnail, with all pixels that changed compared to the other image in red
+    pub fn diff_image(&self, other: &ImageFingerprint) -> RgbImage {
+        RgbImage::from_fn(THUMBNAIL_SIZE, THUMBNAIL_SIZE, |x, y| {
+            let pixel = self.thumbnail.get_pixel(x, y);
+
+            if pixel_changed(pixel, other.thumbnail.get_pixel(x, y)) {
+                Rgb([255, 0, 0])
+            } else {
+                let value = pixel[0] / 2;
+                Rgb([value, value, value])
+            }
+        })
+    }
+}
+
+fn pixel_changed(a: &Luma<u8>, b: &Luma<u8>) -> bool {
+    (a[0] as i16 - b[0] as i16).abs() > PIXEL_TOLERANCE as i16
+}
+
+pub fn encode_png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    image.write_to(&mut data, ImageOutputFormat::Png)?;
+
+    Ok(data)
+}
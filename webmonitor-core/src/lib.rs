@@ -4,28 +4,44 @@
 //! a piece of software to monitor any number of webpages
 //! as well as getting notified whenever they change (and what changed!)
 
-use std::sync::Arc;
+use std::{env, sync::Arc};
 
+use chrono::{TimeZone, Utc};
+use filters::validate_filters;
 use futures::future;
-use model::{InsertableJob, Job};
+use model::{
+    CheckRun, DiffOptions, Filter, InsertableJob, Job, PruneReport, RecomputedSnapshot,
+    RetentionPolicy, SearchHit, SearchQuery, Snapshot, SnapshotDiff, SnapshotPage, SnapshotQuery,
+};
+use mongodb::bson::DateTime;
 use monitoring::WebsiteMonitor;
 use repository::Repository;
 use scheduling::JobScheduler;
+use triggers::validate_triggers;
 
-use crate::error::Result;
+use crate::error::{Result, WebmonitorError};
 
+pub mod detectors;
+pub mod diffing;
 pub mod error;
 pub mod filters;
+pub mod imaging;
 pub mod model;
 pub mod monitoring;
 pub mod notifications;
+pub mod numbers;
 pub mod repository;
+pub mod retention;
 pub mod scheduling;
+pub mod search;
+pub mod triggers;
 
 pub struct Webmonitor {
-    repository: Arc<Repository>,
+    repository: Arc<dyn Repository>,
     monitor: Arc<WebsiteMonitor>,
+    #[allow(dead_code)]
     scheduler: Arc<JobScheduler>,
+    retention_policy: Option<RetentionPolicy>,
 }
 
 impl Webmonitor {
@@ -33,18 +49,50 @@ impl Webmonitor {
     /// and initializes all needed connections and services.
     /// Use this as the starting point of using the crate.
     ///
+    /// The database is chosen by the DATABASE_URI environment variable:
+    /// `sqlite://<path>` stores everything in an SQLite file, `postgres://...` uses PostgreSQL,
+    /// `memory:` keeps everything in memory without persisting it across restarts,
+    /// and any other uri is used to connect to MongoDB (along with DATABASE_NAME).
+    ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let webmonitor = Webmonitor::init().await?;
     /// ```
     ///
     /// # Errors
     ///
     /// The initialization could fail if the database connection couldn't be established.
-    /// In that case, it will return a WebmonitorError::MongoDBError.
+    /// In that case, it will return a WebmonitorError::MongoDBError,
+    /// WebmonitorError::SqliteError or WebmonitorError::PostgresError.
+    /// If DATABASE_URI (or DATABASE_NAME for MongoDB) isn't set,
+    /// it returns a WebmonitorError::MissingEnvironmentVariable.
     pub async fn init() -> Result<Self> {
-        let repository = Arc::new(Repository::init().await?);
+        let uri = env::var("DATABASE_URI")
+            .map_err(|_| WebmonitorError::MissingEnvironmentVariable("DATABASE_URI".into()))?;
+
+        Self::with_repository(repository::init(uri.as_str()).await?).await
+    }
+
+    /// Creates a new instance of the Webmonitor service on top of the given repository,
+    /// and schedules all jobs stored in it.
+    /// Use this to plug in your own storage, or a MemoryRepository in tests.
+    ///
+    /// It also starts a background task that hourly prunes old snapshots according to the
+    /// retention policies of the jobs, or the global policy given by the RETENTION_KEEP_LAST,
+    /// RETENTION_KEEP_DAYS and RETENTION_DAILY_AFTER_DAYS environment variables.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let repository = Arc::new(MemoryRepository::new());
+    /// let webmonitor = Webmonitor::with_repository(repository).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the stored jobs couldn't be loaded from the repository.
+    pub async fn with_repository(repository: Arc<dyn Repository>) -> Result<Self> {
         let monitor = Arc::new(WebsiteMonitor::new(Arc::clone(&repository)));
         let scheduler = Arc::new(JobScheduler::new(Arc::clone(&monitor)));
 
@@ -57,10 +105,14 @@ impl Webmonitor {
         )
         .await;
 
+        let retention_policy = retention::global_policy_from_env();
+        retention::spawn_maintenance(Arc::clone(&repository), retention_policy.clone());
+
         Ok(Self {
             repository,
             monitor,
             scheduler,
+            retention_policy,
         })
     }
 
@@ -71,15 +123,24 @@ impl Webmonitor {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let new_job = InsertableJob {
     ///     name: String::from("Check time every 10 seconds"),
     ///     url: String::from("https://www.unixtimestamp.com/"),
     ///     interval: 10,
     ///     show_diff: true,
+    ///     mode: JobMode::Website,
+    ///     change_detector: ChangeDetector::ExactDetector,
+    ///     triggers: vec![],
+    ///     confirmation: None,
+    ///     ignore_recent_snapshots: 0,
+    ///     retention: None,
+    ///     store_raw: false,
+    ///     store_search_text: false,
     ///     filters: vec![
     ///         Filter::CSSFilter(CSSFilterOptions {
     ///             selector: String::from("div.ui.statistic"),
+    ///             ..Default::default()
     ///         }),
     ///     ],
     ///     notifications: vec![]
@@ -90,9 +151,13 @@ impl Webmonitor {
     ///
     /// # Errors
     ///
-    /// Adding a job can fail if either inserting the record into the database
+    /// Adding a job can fail if one of its filters or triggers is invalid (e.g. a regular
+    /// expression or script that doesn't compile), if inserting the record into the database
     /// fails (for various reasons), or if scheduling the job fails.
     pub async fn add_job(&self, job: InsertableJob) -> Result<Job> {
+        validate_filters(&job.filters)?;
+        validate_triggers(&job.triggers, job.mode)?;
+
         self.repository.jobs_add(job).await
     }
 
@@ -101,7 +166,7 @@ impl Webmonitor {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```ignore
     /// let some_job = webmonitor.get_job("7aw98fa89wf789awf89a").await?;
     ///
     /// if let Some(job) = some_job {
@@ -116,4 +181,324 @@ impl Webmonitor {
     pub async fn get_job(&self, job_id: &str) -> Result<Option<Job>> {
         self.repository.jobs_get_one(job_id).await
     }
+
+    /// Returns the value history of a number job, as all of its snapshots
+    /// that have a value, from oldest to newest.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let history = webmonitor.get_value_history("7aw98fa89wf789awf89a").await?;
+    ///
+    /// for snapshot in history {
+    ///     println!("{}", snapshot.value.unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's a problem with the database connection,
+    /// or when parsing the database documents into Snapshot structs.
+    pub async fn get_value_history(&self, job_id: &str) -> Result<Vec<Snapshot>> {
+        self.repository.snapshots_get_values(job_id).await
+    }
+
+    /// Returns a page of a job's snapshot history, optionally limited to a time range.
+    /// Pass the page's next_cursor along with an otherwise identical query to get the next page.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut query = SnapshotQuery {
+    ///     after: Some(DateTime::from(Utc.timestamp_millis(1_620_000_000_000))),
+    ///     limit: Some(20),
+    ///     ..SnapshotQuery::default()
+    /// };
+    ///
+    /// loop {
+    ///     let page = webmonitor.get_snapshots("7aw98fa89wf789awf89a", &query).await?;
+    ///
+    ///     for snapshot in &page.snapshots {
+    ///         println!("{}", snapshot.created_at);
+    ///     }
+    ///
+    ///     match page.next_cursor {
+    ///         Some(cursor) => query.cursor = Some(cursor),
+    ///         None => break,
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Fails with WebmonitorError::InvalidCursor if the query's cursor wasn't handed out
+    /// by a previous page, or if there's a problem with the database connection.
+    pub async fn get_snapshots(&self, job_id: &str, query: &SnapshotQuery) -> Result<SnapshotPage> {
+        self.repository.snapshots_query(job_id, query).await
+    }
+
+    /// Counts a job's snapshots created at or after `after` and before `before`,
+    /// or all of them if neither is given.
+    ///
+    /// # Errors
+    /// Fails if there's a problem with the database connection.
+    pub async fn count_snapshots(
+        &self,
+        job_id: &str,
+        after: Option<DateTime>,
+        before: Option<DateTime>,
+    ) -> Result<u64> {
+        self.repository.snapshots_count(job_id, after, before).await
+    }
+
+    /// Returns the snapshot of a job the given number of versions before its latest one
+    /// (so 0 returns the latest snapshot), or None if the job doesn't have that many snapshots.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let previous = webmonitor.get_snapshot_versions_back("7aw98fa89wf789awf89a", 1).await?;
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's a problem with the database connection,
+    /// or when parsing the database document into the Snapshot struct.
+    pub async fn get_snapshot_versions_back(
+        &self,
+        job_id: &str,
+        versions: u64,
+    ) -> Result<Option<Snapshot>> {
+        self.repository
+            .snapshots_get_versions_back(job_id, versions)
+            .await
+    }
+
+    /// Compares the data of two snapshots line by line, and returns the changed lines
+    /// grouped into hunks, along with the number of added and removed lines.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let diff = webmonitor
+    ///     .diff_snapshots(
+    ///         "60a7c5f0e4b0a1b2c3d4e5f6",
+    ///         "60a8e1b2e4b0a1b2c3d4e5f7",
+    ///         &DiffOptions::default(),
+    ///     )
+    ///     .await?;
+    ///
+    /// println!("+{} -{}\n{}", diff.added, diff.removed, diff.rendered);
+    /// ```
+    ///
+    /// # Errors
+    /// Fails with WebmonitorError::SnapshotNotFound if one of the snapshots doesn't exist,
+    /// with WebmonitorError::SnapshotJobMismatch if they belong to different jobs,
+    /// or if there's a problem with the database connection.
+    pub async fn diff_snapshots(
+        &self,
+        old_snapshot_id: &str,
+        new_snapshot_id: &str,
+        options: &DiffOptions,
+    ) -> Result<SnapshotDiff> {
+        let old = self.find_snapshot(old_snapshot_id).await?;
+        let new = self.find_snapshot(new_snapshot_id).await?;
+
+        if old.job_id != new.job_id {
+            return Err(WebmonitorError::SnapshotJobMismatch(old.id, new.id));
+        }
+
+        Ok(diffing::diff_snapshots(&old, &new, options))
+    }
+
+    /// Compares what a job's content was at two points in time, using the latest snapshot
+    /// at or before each of them, e.g. to see what changed since last week.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let now = Utc::now();
+    ///
+    /// let diff = webmonitor
+    ///     .diff_job_between(
+    ///         "7aw98fa89wf789awf89a",
+    ///         DateTime::from(now - chrono::Duration::days(7)),
+    ///         DateTime::from(now),
+    ///         &DiffOptions::default(),
+    ///     )
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    /// Fails with WebmonitorError::NoSnapshotAt if the job has no snapshot at or before
+    /// one of the times, or if there's a problem with the database connection.
+    pub async fn diff_job_between(
+        &self,
+        job_id: &str,
+        from: DateTime,
+        to: DateTime,
+        options: &DiffOptions,
+    ) -> Result<SnapshotDiff> {
+        let old = self.snapshot_at(job_id, from).await?;
+        let new = self.snapshot_at(job_id, to).await?;
+
+        Ok(diffing::diff_snapshots(&old, &new, options))
+    }
+
+    /// Searches the snapshot history of all jobs (or a single one) for a phrase, and returns
+    /// the matching snapshots of all jobs together from oldest to newest,
+    /// with an excerpt of the match.
+    ///
+    /// The snapshots are found with the database's text index. PostgreSQL's index only finds
+    /// whole words, so with PostgreSQL, text that starts or ends within a word isn't found.
+    /// MongoDB only searches quickly through jobs with the store_search_text option.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let query = SearchQuery {
+    ///     text: String::from("sold out"),
+    ///     first_only: true,
+    ///     ..SearchQuery::default()
+    /// };
+    ///
+    /// for hit in webmonitor.search_snapshots(&query).await? {
+    ///     println!("{} first showed it at {}: {}", hit.job_name, hit.created_at, hit.excerpt);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's a problem with the database connection,
+    /// or when parsing the database documents into Snapshot structs.
+    pub async fn search_snapshots(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        search::search_snapshots(self.repository.as_ref(), query).await
+    }
+
+    /// Returns the most recent check run of a job, telling when it was last checked
+    /// and whether that check found a change or failed, or None if it wasn't checked yet.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let last_run = webmonitor.get_last_check_run("7aw98fa89wf789awf89a").await?;
+    ///
+    /// if let Some(run) = last_run {
+    ///     println!("{:?}", run.outcome);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's a problem with the database connection,
+    /// or when parsing the database document into the CheckRun struct.
+    pub async fn get_last_check_run(&self, job_id: &str) -> Result<Option<CheckRun>> {
+        self.repository.check_runs_get_latest(job_id).await
+    }
+
+    /// Returns all check runs of a job, from newest to oldest.
+    ///
+    /// # Errors
+    /// Fails if there's a problem with the database connection,
+    /// or when parsing the database documents into CheckRun structs.
+    pub async fn get_check_runs(&self, job_id: &str) -> Result<Vec<CheckRun>> {
+        self.repository.check_runs_get_all(job_id).await
+    }
+
+    /// Re-runs the job's current filter chain over the unfiltered content stored with its
+    /// snapshots (see the job's store_raw option), and returns the resulting change history
+    /// from oldest to newest. Useful after changing a job's filters, to see which of the
+    /// past changes would still have been detected.
+    ///
+    /// Note that snapshots, and with them the unfiltered content, are only stored when a check
+    /// detected a change with the filters the job had at that time. Content that would only
+    /// have been a change with the new filters was never stored, so it's missing from the
+    /// recomputed history.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let history = webmonitor.recompute_history("7aw98fa89wf789awf89a").await?;
+    ///
+    /// for snapshot in history.iter().filter(|snapshot| snapshot.changed) {
+    ///     println!("{}", snapshot.data);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Fails with WebmonitorError::JobNotFound if there's no job with the given id,
+    /// with WebmonitorError::UnsupportedJobMode if it isn't a website job,
+    /// if one of the filters fails, or if there's a problem with the database connection.
+    pub async fn recompute_history(&self, job_id: &str) -> Result<Vec<RecomputedSnapshot>> {
+        let job = self.find_job(job_id).await?;
+
+        self.monitor.recompute_history(&job, &job.filters).await
+    }
+
+    /// Like recompute_history, but applies the given filters instead of the job's own ones,
+    /// to preview how a changed filter chain would have behaved on the job's history.
+    /// It's subject to the same limitation: only content of checks that stored a snapshot
+    /// with the filters at that time can be previewed.
+    ///
+    /// # Errors
+    /// Fails if one of the filters is invalid, or for the same reasons as recompute_history.
+    pub async fn preview_filters(
+        &self,
+        job_id: &str,
+        filters: &[Filter],
+    ) -> Result<Vec<RecomputedSnapshot>> {
+        validate_filters(filters)?;
+        let job = self.find_job(job_id).await?;
+
+        self.monitor.recompute_history(&job, filters).await
+    }
+
+    /// Immediately prunes the snapshots of all jobs according to their retention policies
+    /// (or the global one), instead of waiting for the background maintenance task,
+    /// and reports how many snapshots and bytes of stored data were removed.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let report = webmonitor.prune_snapshots().await?;
+    ///
+    /// println!(
+    ///     "Removed {} snapshots, freeing {} bytes",
+    ///     report.snapshots_removed, report.bytes_removed
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's a problem with the database connection.
+    pub async fn prune_snapshots(&self) -> Result<PruneReport> {
+        retention::prune_all_jobs(self.repository.as_ref(), self.retention_policy.as_ref()).await
+    }
+
+    async fn find_snapshot(&self, snapshot_id: &str) -> Result<Snapshot> {
+        self.repository
+            .snapshots_get_one(snapshot_id)
+            .await?
+            .ok_or_else(|| WebmonitorError::SnapshotNotFound(snapshot_id.to_string()))
+    }
+
+    /// Returns the latest snapshot of the job created at or before the given time
+    async fn snapshot_at(&self, job_id: &str, time: DateTime) -> Result<Snapshot> {
+        let query = SnapshotQuery {
+            before: Some(DateTime::from(
+                Utc.timestamp_millis(time.timestamp_millis() + 1),
+            )),
+            limit: Some(1),
+            ..SnapshotQuery::default()
+        };
+
+        self.repository
+            .snapshots_query(job_id, &query)
+            .await?
+            .snapshots
+            .into_iter()
+            .next()
+            .ok_or_else(|| WebmonitorError::NoSnapshotAt(job_id.to_string()))
+    }
+
+    async fn find_job(&self, job_id: &str) -> Result<Job> {
+        self.repository
+            .jobs_get_one(job_id)
+            .await?
+            .ok_or_else(|| WebmonitorError::JobNotFound(job_id.to_string()))
+    }
 }
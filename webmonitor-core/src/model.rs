@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
+
 use bson::serde_helpers::{hex_string_as_object_id, serialize_u64_as_i64};
-use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Deserializer, Serialize};
 
 pub fn deserialize_object_id_to_hex_string<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -20,6 +22,34 @@ pub struct Job {
     pub url: String,
     pub show_diff: bool,
 
+    #[serde(default)]
+    pub mode: JobMode,
+    #[serde(default)]
+    pub change_detector: ChangeDetector,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+
+    /// Only accept changes after they've been seen on multiple checks (website jobs only)
+    #[serde(default)]
+    pub confirmation: Option<ConfirmationOptions>,
+    /// Ignore content that equals one of this many recent snapshots (website jobs only)
+    #[serde(default)]
+    pub ignore_recent_snapshots: u32,
+    /// Which snapshots to keep, overriding the global retention policy
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>,
+    /// Also store the unfiltered content with each snapshot, so filters can be
+    /// re-applied to the job's history later (website jobs only)
+    #[serde(default)]
+    pub store_raw: bool,
+    /// Also store the content of snapshots uncompressed in MongoDB, so searches can match it
+    /// on the server. Every distinct content then takes its full size a second time,
+    /// often several times what its compressed copy takes. Searching jobs without this
+    /// loads and decompresses all of their snapshots in the searched range instead.
+    /// The other databases index the content without storing a copy, so they ignore this.
+    #[serde(default)]
+    pub store_search_text: bool,
+
     #[serde(serialize_with = "serialize_u64_as_i64")]
     pub interval: u64,
     pub filters: Vec<Filter>,
@@ -32,12 +62,118 @@ pub struct InsertableJob {
     pub url: String,
     pub show_diff: bool,
 
+    #[serde(default)]
+    pub mode: JobMode,
+    #[serde(default)]
+    pub change_detector: ChangeDetector,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+
+    /// Only accept changes after they've been seen on multiple checks (website jobs only)
+    #[serde(default)]
+    pub confirmation: Option<ConfirmationOptions>,
+    /// Ignore content that equals one of this many recent snapshots (website jobs only)
+    #[serde(default)]
+    pub ignore_recent_snapshots: u32,
+    /// Which snapshots to keep, overriding the global retention policy
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>,
+    /// Also store the unfiltered content with each snapshot, so filters can be
+    /// re-applied to the job's history later (website jobs only)
+    #[serde(default)]
+    pub store_raw: bool,
+    /// Also store the content of snapshots uncompressed in MongoDB, so searches can match it
+    /// on the server. Every distinct content then takes its full size a second time,
+    /// often several times what its compressed copy takes. Searching jobs without this
+    /// loads and decompresses all of their snapshots in the searched range instead.
+    /// The other databases index the content without storing a copy, so they ignore this.
+    #[serde(default)]
+    pub store_search_text: bool,
+
     #[serde(serialize_with = "serialize_u64_as_i64")]
     pub interval: u64,
     pub filters: Vec<Filter>,
     pub notifications: Vec<Notification>,
 }
 
+// The way a Job's url is checked for changes
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum JobMode {
+    /// Fetches the page, applies the filters and compares the result to the previous snapshot.
+    /// The text of PDF documents is extracted before the filters are applied.
+    #[default]
+    Website,
+    /// Parses the url as an RSS/Atom feed and reports items that weren't seen before.
+    /// Filters are not applied in this mode.
+    Feed,
+    /// Streams the url's file and compares its hash and size to the previous snapshot,
+    /// without storing the file itself. Filters are not applied in this mode.
+    File,
+    /// Decodes the url's image and compares it to the previous snapshot by perceptual hash
+    /// and share of changed pixels. Filters are not applied in this mode.
+    Image(ImageModeOptions),
+    /// Fetches the page, applies the filters and parses the first number of the result,
+    /// which gets stored as the snapshot's value and compared to the previous value.
+    Number(NumberModeOptions),
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImageModeOptions {
+    /// Minimum share of changed pixels (in percent) for the image to count as changed
+    pub threshold: f64,
+    /// Minimum number of differing perceptual hash bits (out of 64) for the image
+    /// to count as changed, which ignores re-encoded but otherwise identical images.
+    /// Defaults to 1, while 0 relies on the share of changed pixels alone.
+    #[serde(default)]
+    pub min_perceptual_distance: Option<u32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NumberModeOptions {
+    /// The decimal separator of the number, guessed from the number itself if not given
+    pub decimal_separator: Option<char>,
+    pub trigger: NumberTrigger,
+}
+
+// When to notify about a changed value of a number job
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NumberTrigger {
+    AnyChange,
+    DropsBelow(f64),
+    RisesAbove(f64),
+    DropsByPercent(f64),
+    RisesByPercent(f64),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConfirmationOptions {
+    /// Number of consecutive checks the changed content needs to be seen on
+    pub checks: u32,
+    /// Recheck the content after this many seconds, instead of on the next scheduled checks
+    pub recheck_delay: Option<u64>,
+}
+
+// Rules for which snapshots of a Job are pruned. A snapshot is removed as soon as one
+// of the rules says so, but the latest snapshot of a job is always kept.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Keep only this many of the most recent snapshots
+    pub keep_last: Option<u32>,
+    /// Remove snapshots older than this many days
+    pub keep_days: Option<u32>,
+    /// Keep only the last snapshot of each day for snapshots older than this many days
+    pub daily_after_days: Option<u32>,
+}
+
+// How much was removed by pruning snapshots. The removed bytes are the stored (compressed)
+// size of the bodies no snapshot references anymore, so deleting snapshots whose bodies
+// are still shared with other snapshots doesn't free any.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PruneReport {
+    pub snapshots_removed: u64,
+    pub bytes_removed: u64,
+}
+
 // Snapshots of a Job
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Snapshot {
@@ -46,12 +182,263 @@ pub struct Snapshot {
 
     pub job_id: String,
     pub data: String,
+    /// The parsed value, for snapshots of number jobs
+    pub value: Option<f64>,
+    /// The unfiltered content, for snapshots of jobs that store it.
+    /// Snapshots of image jobs store the full image here, as a base64 encoded PNG.
+    #[serde(default)]
+    pub raw_data: Option<String>,
+
+    pub created_at: DateTime,
+    /// Details of the request the snapshot's data was fetched with
+    #[serde(default)]
+    pub fetch: Option<FetchMetadata>,
+}
+
+// Id and creation time of a Snapshot, without loading its data
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    #[serde(rename = "_id", with = "hex_string_as_object_id")]
+    pub id: String,
+    pub created_at: DateTime,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct InsertableSnapshot {
     pub job_id: String,
     pub data: String,
+    pub value: Option<f64>,
+    pub raw_data: Option<String>,
+
+    pub created_at: DateTime,
+    pub fetch: Option<FetchMetadata>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FetchMetadata {
+    pub status: u16,
+    /// The url the content was fetched from, after following redirects
+    pub final_url: String,
+    /// A selection of response headers that describe the content
+    pub headers: BTreeMap<String, String>,
+
+    #[serde(serialize_with = "serialize_u64_as_i64")]
+    pub duration_millis: u64,
+    #[serde(serialize_with = "serialize_u64_as_i64")]
+    pub byte_size: u64,
+}
+
+// Which part of a Job's snapshot history to return, and in which order
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotQuery {
+    /// Only return snapshots created at or after this time
+    pub after: Option<DateTime>,
+    /// Only return snapshots created before this time
+    pub before: Option<DateTime>,
+    /// The maximum number of snapshots per page, or all of them if None
+    pub limit: Option<u32>,
+    /// The next_cursor of the previous page, to continue where it ended
+    pub cursor: Option<String>,
+    pub order: SortOrder,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+// A page of a Job's snapshot history
+#[derive(Clone, Debug)]
+pub struct SnapshotPage {
+    pub snapshots: Vec<Snapshot>,
+    /// The cursor to query the following page with, or None if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+// Options for diffing two snapshots on demand
+#[derive(Clone, Copy, Debug)]
+pub struct DiffOptions {
+    /// Number of unchanged lines shown before and after each change
+    pub context_lines: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions { context_lines: 3 }
+    }
+}
+
+// The line by line differences between the data of two snapshots
+#[derive(Clone, Debug)]
+pub struct SnapshotDiff {
+    pub old_snapshot_id: String,
+    pub new_snapshot_id: String,
+    pub old_created_at: DateTime,
+    pub new_created_at: DateTime,
+
+    pub hunks: Vec<DiffHunk>,
+    /// Number of added lines
+    pub added: usize,
+    /// Number of removed lines
+    pub removed: usize,
+    /// The hunks as text, with the same line prefixes as notifications
+    pub rendered: String,
+}
+
+// A group of changed lines along with the unchanged lines around them
+#[derive(Clone, Debug)]
+pub struct DiffHunk {
+    /// The first line of the hunk in the old data, starting at 1
+    pub old_start: usize,
+    pub old_lines: usize,
+    /// The first line of the hunk in the new data, starting at 1
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DiffLine {
+    pub tag: DiffTag,
+    pub content: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffTag {
+    Equal,
+    Insert,
+    Delete,
+}
+
+// What to search the snapshot history for
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    /// The text to search for, which is matched literally
+    pub text: String,
+    pub case_sensitive: bool,
+    /// Only search the snapshots of this job
+    pub job_id: Option<String>,
+    /// Only search snapshots created at or after this time
+    pub after: Option<DateTime>,
+    /// Only search snapshots created before this time
+    pub before: Option<DateTime>,
+    /// Only return the oldest matching snapshot of each job, i.e. when the text first appeared
+    pub first_only: bool,
+    /// The maximum number of results, or all of them if None
+    pub limit: Option<u32>,
+}
+
+// A snapshot that contains the searched text
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub job_id: String,
+    pub job_name: String,
+    pub snapshot_id: String,
+    pub created_at: DateTime,
+    /// The text around the first match, with the match wrapped in `**`
+    pub excerpt: String,
+}
+
+// A snapshot's content after re-applying filters to its stored unfiltered content
+#[derive(Clone, Debug)]
+pub struct RecomputedSnapshot {
+    pub snapshot_id: String,
+    pub created_at: DateTime,
+    pub data: String,
+    /// Whether the recomputed content changed compared to the previous recomputed snapshot
+    pub changed: bool,
+}
+
+// Records of every check of a Job, whether it found a change or not
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckRun {
+    #[serde(rename = "_id", with = "hex_string_as_object_id")]
+    pub id: String,
+
+    pub job_id: String,
+    pub started_at: DateTime,
+    pub finished_at: DateTime,
+    pub outcome: CheckOutcome,
+    pub error: Option<String>,
+    /// The snapshot that was recorded, if the check found a change
+    pub snapshot_id: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InsertableCheckRun {
+    pub job_id: String,
+    pub started_at: DateTime,
+    pub finished_at: DateTime,
+    pub outcome: CheckOutcome,
+    pub error: Option<String>,
+    pub snapshot_id: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CheckOutcome {
+    Unchanged,
+    Changed,
+    Error,
+}
+
+// Decides whether the content of a Job changed compared to its previous snapshot
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub enum ChangeDetector {
+    #[default]
+    ExactDetector,
+    IgnoreWhitespaceDetector,
+    ThresholdDetector(ThresholdDetectorOptions),
+    AdditionsDetector,
+    RemovalsDetector,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThresholdDetectorOptions {
+    /// Minimum share of changed lines or characters (in percent) to count as a change
+    pub min_changed_percent: f64,
+    pub unit: DiffUnit,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum DiffUnit {
+    Lines,
+    Chars,
+}
+
+// Conditions on the content of a Job that all need to be met for notifications to be sent.
+// They're evaluated on the filtered content of website jobs and the new items of feed jobs,
+// and don't affect whether snapshots are recorded. Jobs of other modes can't have triggers.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Trigger {
+    ContainsTrigger(TextTriggerOptions),
+    NotContainsTrigger(TextTriggerOptions),
+    MatchesTrigger(RegexTriggerOptions),
+    NotMatchesTrigger(RegexTriggerOptions),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TextTriggerOptions {
+    pub text: String,
+    /// Only fire when the previous content didn't meet the condition yet
+    #[serde(default)]
+    pub transition_only: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegexTriggerOptions {
+    pub pattern: String,
+    /// Only fire when the previous content didn't meet the condition yet
+    #[serde(default)]
+    pub transition_only: bool,
+}
+
+// Feed items that have already been reported for a Job
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FeedItem {
+    pub job_id: String,
+    pub item_id: String,
 }
 
 // Filters to apply to Jobs
@@ -60,11 +447,47 @@ pub enum Filter {
     CSSFilter(CSSFilterOptions),
     XPathFilter(XPathFilterOptions),
     Html2TextFilter,
+    Html2MarkdownFilter,
+    JsonPathFilter(JsonPathFilterOptions),
+    RegexExtractFilter(RegexExtractFilterOptions),
+    RegexReplaceFilter(RegexReplaceFilterOptions),
+    TrimLinesFilter,
+    CollapseWhitespaceFilter,
+    RemoveBlankLinesFilter,
+    SortLinesFilter,
+    DeduplicateLinesFilter,
+    CaseFoldFilter,
+    UnicodeNormalizeFilter(UnicodeNormalizeFilterOptions),
+    ScriptFilter(ScriptFilterOptions),
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct CSSFilterOptions {
     pub selector: String,
+
+    /// What to output for every matched element
+    #[serde(default)]
+    pub output: CSSFilterOutput,
+    /// Outputs the value of this attribute instead, skipping elements that don't have it
+    #[serde(default)]
+    pub attribute: Option<String>,
+    /// Elements matching this selector are removed before any output is generated
+    #[serde(default)]
+    pub exclude_selector: Option<String>,
+    /// Only outputs the match at this (zero-based) index
+    #[serde(default)]
+    pub nth: Option<usize>,
+    /// Inserted between the outputs of all matched elements
+    #[serde(default)]
+    pub separator: String,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum CSSFilterOutput {
+    #[default]
+    Html,
+    InnerHtml,
+    Text,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -72,6 +495,45 @@ pub struct XPathFilterOptions {
     pub selector: String,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JsonPathFilterOptions {
+    pub selector: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegexExtractFilterOptions {
+    pub pattern: String,
+    /// Name of the capture group to keep, instead of the whole match
+    pub group: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegexReplaceFilterOptions {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UnicodeNormalizeFilterOptions {
+    pub form: UnicodeNormalizationForm,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum UnicodeNormalizationForm {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScriptFilterOptions {
+    /// Rhai source code, which gets the content as `input` and evaluates to the output
+    pub script: String,
+    pub max_operations: Option<u64>,
+    pub timeout_millis: Option<u64>,
+}
+
 // Notifiers to send out notifications for Jobs
 #[derive(Clone, Serialize, Deserialize)]
 pub enum Notification {
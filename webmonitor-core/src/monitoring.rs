@@ -1,81 +1,803 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use bytes::Bytes;
+use chrono::Utc;
+use encoding_rs::{Encoding, UTF_8};
+use feed_rs::model::Entry;
 use futures::future;
+use image::DynamicImage;
+use log::error;
+use mongodb::bson::DateTime;
+use reqwest::{
+    header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, LAST_MODIFIED},
+    Response,
+};
+use sha2::{Digest, Sha256};
+use tokio::{sync::Mutex, time};
 
 use crate::{
-    error::Result,
-    filters::{CSSFilter, FilterApply, Html2TextFilter, XPathFilter},
-    model::{Filter, InsertableSnapshot, Job, Notification},
-    notifications::{DiscordNotification, EmailNotification, NotificationSend},
+    detectors::{
+        AdditionsDetector, ChangeDetect, ExactDetector, IgnoreWhitespaceDetector, RemovalsDetector,
+        ThresholdDetector,
+    },
+    error::{Result, WebmonitorError},
+    filters::{
+        CSSFilter, CaseFoldFilter, CollapseWhitespaceFilter, DeduplicateLinesFilter, FilterApply,
+        Html2MarkdownFilter, Html2TextFilter, JsonPathFilter, PdfTextFilter, RegexExtractFilter,
+        RegexReplaceFilter, RemoveBlankLinesFilter, ScriptFilter, SortLinesFilter, TrimLinesFilter,
+        UnicodeNormalizeFilter, XPathFilter,
+    },
+    imaging::{encode_png, ImageFingerprint, DEFAULT_MIN_PERCEPTUAL_DISTANCE},
+    model::{
+        ChangeDetector, CheckOutcome, ConfirmationOptions, FeedItem, FetchMetadata, Filter,
+        ImageModeOptions, InsertableCheckRun, InsertableSnapshot, Job, JobMode, Notification,
+        NumberModeOptions, RecomputedSnapshot, Snapshot, SnapshotQuery, SortOrder,
+    },
+    notifications::{Attachment, DiscordNotification, EmailNotification, NotificationSend},
+    numbers::{number_trigger_met, parse_number},
     repository::Repository,
+    triggers::triggers_met,
 };
 
+// How many snapshots are loaded at once when recomputing a job's history
+const RECOMPUTE_PAGE_SIZE: u32 = 100;
+
+// Changed content of a job that's waiting to be confirmed by further checks
+struct PendingChange {
+    content: String,
+    count: u32,
+}
+
 pub struct WebsiteMonitor {
-    db: Arc<Repository>,
+    db: Arc<dyn Repository>,
+    pending_changes: Mutex<HashMap<String, PendingChange>>,
 }
 
 impl WebsiteMonitor {
-    pub fn new(db: Arc<Repository>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<dyn Repository>) -> Self {
+        Self {
+            db,
+            pending_changes: Mutex::new(HashMap::new()),
+        }
     }
 
-    pub async fn run_website_check_for_job(&self, job: &Job) -> Result<()> {
-        let website_dom = reqwest::get(&job.url).await?.text().await?;
+    /// Runs the check matching the job's mode, and records the run with its outcome.
+    /// Returns the result of the check, even if recording the run fails.
+    pub async fn run_check_for_job(&self, job: &Job) -> Result<()> {
+        let started_at = DateTime::from(Utc::now());
 
-        let filtered_dom = self.apply_filters(website_dom, &job.filters)?;
+        let result = match job.mode {
+            JobMode::Website => self.run_website_check_for_job(job).await,
+            JobMode::Feed => self.run_feed_check_for_job(job).await,
+            JobMode::File => self.run_file_check_for_job(job).await,
+            JobMode::Image(options) => self.run_image_check_for_job(job, options).await,
+            JobMode::Number(options) => self.run_number_check_for_job(job, options).await,
+        };
+
+        let (outcome, error, snapshot_id) = match &result {
+            Ok(Some(snapshot)) => (CheckOutcome::Changed, None, Some(snapshot.id.clone())),
+            Ok(None) => (CheckOutcome::Unchanged, None, None),
+            Err(e) => (CheckOutcome::Error, Some(e.to_string()), None),
+        };
+
+        let check_run = InsertableCheckRun {
+            job_id: job.id.clone(),
+            started_at,
+            finished_at: Utc::now().into(),
+            outcome,
+            error,
+            snapshot_id,
+        };
+        // Failing to record the run shouldn't hide the outcome of the check itself
+        if let Err(e) = self.db.check_runs_add(check_run).await {
+            error!("Failed to record the check run of job '{}': {}", job.id, e);
+        }
+
+        result.map(|_| ())
+    }
+
+    /// Checks whether the job's filtered page changed. Like all checks,
+    /// it returns the newly recorded snapshot if there was a change.
+    pub async fn run_website_check_for_job(&self, job: &Job) -> Result<Option<Snapshot>> {
+        let (website_dom, fetch) = self.fetch_website(&job.url).await?;
+
+        let raw_data = if job.store_raw {
+            Some(website_dom.clone())
+        } else {
+            None
+        };
+        let filtered_dom = self.apply_filters(website_dom, &job.filters).await?;
 
         let prev_snapshot = self.db.snapshots_get_latest(&job.id).await?;
 
-        if prev_snapshot.is_none()
-            || self.dom_has_changed(&prev_snapshot.clone().unwrap().data, &filtered_dom)
-        {
-            let data = InsertableSnapshot {
-                job_id: (&job.id).clone(),
-                data: filtered_dom,
-            };
-            let new_snapshot = self.db.snapshots_add(data).await?;
-
-            let notifications = &job.notifications;
-            future::join_all(notifications.into_iter().map(|notification| {
-                let new_snap = &new_snapshot;
-                let prev_snap = &prev_snapshot;
-
-                async move {
-                    match notification {
-                        Notification::Discord(options) => {
-                            DiscordNotification::with_options(options.clone())
-                                .send(job, &prev_snap, &new_snap)
-                                .await
-                        }
-                        Notification::Email(options) => {
-                            EmailNotification::with_options(options.clone())
-                                .send(job, &prev_snap, &new_snap)
-                                .await
-                        }
-                    };
+        if let Some(prev) = &prev_snapshot {
+            if !self.dom_has_changed(&job.change_detector, &prev.data, &filtered_dom) {
+                self.pending_changes.lock().await.remove(&job.id);
+                return Ok(None);
+            }
+
+            if self.matches_recent_snapshot(job, &filtered_dom).await? {
+                return Ok(None);
+            }
+
+            if let Some(options) = &job.confirmation {
+                if !self.change_confirmed(job, options, &filtered_dom).await? {
+                    return Ok(None);
                 }
-            }))
-            .await;
+            }
         }
 
-        Ok(())
+        let data = InsertableSnapshot {
+            job_id: job.id.clone(),
+            data: filtered_dom,
+            value: None,
+            raw_data,
+            created_at: Utc::now().into(),
+            fetch: Some(fetch),
+        };
+        let new_snapshot = self.db.snapshots_add(data).await?;
+
+        if self.triggers_met(job, &prev_snapshot, &new_snapshot)? {
+            self.send_notifications(job, &prev_snapshot, &new_snapshot, &[])
+                .await;
+        }
+
+        Ok(Some(new_snapshot))
     }
 
-    fn apply_filters(&self, dom: String, filters: &Vec<Filter>) -> Result<String> {
-        filters
+    /// Re-applies the given filters to the stored unfiltered content of the job's snapshots,
+    /// from oldest to newest, and detects changes between the results with the job's
+    /// change detector. Snapshots without stored unfiltered content are skipped.
+    ///
+    /// Snapshots are loaded a page at a time, so only the filtered results are kept in memory.
+    pub async fn recompute_history(
+        &self,
+        job: &Job,
+        filters: &[Filter],
+    ) -> Result<Vec<RecomputedSnapshot>> {
+        if job.mode != JobMode::Website {
+            return Err(WebmonitorError::UnsupportedJobMode(job.id.clone()));
+        }
+
+        let mut query = SnapshotQuery {
+            limit: Some(RECOMPUTE_PAGE_SIZE),
+            order: SortOrder::OldestFirst,
+            ..Default::default()
+        };
+
+        let mut history: Vec<RecomputedSnapshot> = Vec::new();
+        loop {
+            let page = self.db.snapshots_query(&job.id, &query).await?;
+
+            for snapshot in page.snapshots {
+                let raw_data = match snapshot.raw_data {
+                    Some(raw_data) => raw_data,
+                    None => continue,
+                };
+
+                let data = self.apply_filters(raw_data, filters).await?;
+                let changed = history.last().is_none_or(|prev| {
+                    self.dom_has_changed(&job.change_detector, &prev.data, &data)
+                });
+
+                history.push(RecomputedSnapshot {
+                    snapshot_id: snapshot.id,
+                    created_at: snapshot.created_at,
+                    data,
+                    changed,
+                });
+            }
+
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Returns whether the content equals one of the job's recent snapshots,
+    /// as configured by its ignore_recent_snapshots option
+    async fn matches_recent_snapshot(&self, job: &Job, content: &str) -> Result<bool> {
+        if job.ignore_recent_snapshots == 0 {
+            return Ok(false);
+        }
+
+        let recent_snapshots = self
+            .db
+            .snapshots_get_recent(&job.id, job.ignore_recent_snapshots as i64)
+            .await?;
+
+        Ok(recent_snapshots
+            .iter()
+            .any(|snap| !self.dom_has_changed(&job.change_detector, &snap.data, content)))
+    }
+
+    /// Returns whether the changed content has been confirmed by enough consecutive checks.
+    /// With a recheck delay, the content is rechecked right away, otherwise the
+    /// confirmations are counted over the following scheduled checks.
+    async fn change_confirmed(
+        &self,
+        job: &Job,
+        options: &ConfirmationOptions,
+        content: &str,
+    ) -> Result<bool> {
+        if let Some(delay) = options.recheck_delay {
+            for _ in 1..options.checks {
+                time::sleep(Duration::from_secs(delay)).await;
+
+                let (website_dom, _) = self.fetch_website(&job.url).await?;
+                let recheck_dom = self.apply_filters(website_dom, &job.filters).await?;
+
+                if self.dom_has_changed(&job.change_detector, content, &recheck_dom) {
+                    return Ok(false);
+                }
+            }
+
+            return Ok(true);
+        }
+
+        let mut pending_changes = self.pending_changes.lock().await;
+
+        let prev_count = pending_changes
+            .get(&job.id)
+            .filter(|pending| {
+                !self.dom_has_changed(&job.change_detector, &pending.content, content)
+            })
+            .map_or(0, |pending| pending.count);
+        let count = prev_count + 1;
+
+        if count >= options.checks {
+            pending_changes.remove(&job.id);
+            return Ok(true);
+        }
+
+        pending_changes.insert(
+            job.id.clone(),
+            PendingChange {
+                content: String::from(content),
+                count,
+            },
+        );
+
+        Ok(false)
+    }
+
+    /// Checks the job's feed for items that haven't been reported yet.
+    /// Items are identified by their guid (or link), so reordering the feed
+    /// or dropping old items won't cause them to be reported again.
+    pub async fn run_feed_check_for_job(&self, job: &Job) -> Result<Option<Snapshot>> {
+        let (body, fetch) = self.fetch_bytes(&job.url).await?;
+        let feed = feed_rs::parser::parse(body.as_ref())?;
+
+        let mut seen_items: HashSet<String> = self
+            .db
+            .feed_items_get_all(&job.id)
+            .await?
             .into_iter()
-            .try_fold(dom, |filtered_dom, filter| match filter {
-                Filter::CSSFilter(options) => {
-                    CSSFilter::with_options(options.clone()).apply(filtered_dom)
+            .map(|item| item.item_id)
+            .collect();
+
+        let new_entries: Vec<&Entry> = feed
+            .entries
+            .iter()
+            .filter(|entry| seen_items.insert(entry.id.clone()))
+            .collect();
+
+        if new_entries.is_empty() {
+            return Ok(None);
+        }
+
+        let prev_snapshot = self.db.snapshots_get_latest(&job.id).await?;
+
+        let data = InsertableSnapshot {
+            job_id: job.id.clone(),
+            data: new_entries
+                .iter()
+                .map(|entry| self.format_feed_entry(entry))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            value: None,
+            raw_data: None,
+            created_at: Utc::now().into(),
+            fetch: Some(fetch),
+        };
+        let new_snapshot = self.db.snapshots_add(data).await?;
+
+        self.db
+            .feed_items_add(
+                new_entries
+                    .iter()
+                    .map(|entry| FeedItem {
+                        job_id: job.id.clone(),
+                        item_id: entry.id.clone(),
+                    })
+                    .collect(),
+            )
+            .await?;
+
+        if self.triggers_met(job, &prev_snapshot, &new_snapshot)? {
+            self.send_notifications(job, &prev_snapshot, &new_snapshot, &[])
+                .await;
+        }
+
+        Ok(Some(new_snapshot))
+    }
+
+    /// Checks whether the job's file changed, by streaming it and comparing
+    /// its hash and size to the previous snapshot. Only this summary and a few
+    /// descriptive headers are stored, never the file itself.
+    pub async fn run_file_check_for_job(&self, job: &Job) -> Result<Option<Snapshot>> {
+        let started = Instant::now();
+        let mut response = reqwest::get(&job.url).await?;
+        let mut fetch = response_metadata(&response);
+
+        let headers: Vec<String> = [CONTENT_TYPE, ETAG, LAST_MODIFIED]
+            .iter()
+            .filter_map(|name| {
+                let value = response.headers().get(name)?.to_str().ok()?;
+                Some(format!("{}: {}", name, value))
+            })
+            .collect();
+
+        let mut hasher = Sha256::new();
+        let mut size: u64 = 0;
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+        }
+
+        fetch.byte_size = size;
+        fetch.duration_millis = started.elapsed().as_millis() as u64;
+
+        let mut summary = vec![
+            format!("sha256: {:x}", hasher.finalize()),
+            format!("size: {} bytes", size),
+        ];
+        summary.extend(headers);
+        let file_summary = summary.join("\n");
+
+        let prev_snapshot = self.db.snapshots_get_latest(&job.id).await?;
+
+        if let Some(prev) = &prev_snapshot {
+            if !self.file_has_changed(&prev.data, &file_summary) {
+                return Ok(None);
+            }
+        }
+
+        let data = InsertableSnapshot {
+            job_id: job.id.clone(),
+            data: file_summary,
+            value: None,
+            raw_data: None,
+            created_at: Utc::now().into(),
+            fetch: Some(fetch),
+        };
+        let new_snapshot = self.db.snapshots_add(data).await?;
+
+        self.send_notifications(job, &prev_snapshot, &new_snapshot, &[])
+            .await;
+
+        Ok(Some(new_snapshot))
+    }
+
+    /// Checks whether the job's image changed noticeably. The image is compared to
+    /// the image of the previous snapshot, and only counts as changed if both the perceptual
+    /// hashes differ enough and the share of changed pixels reaches the job's threshold.
+    /// The full image is stored with the snapshot, so the current and previous image
+    /// can be attached to the notifications, along with a diff.
+    pub async fn run_image_check_for_job(
+        &self,
+        job: &Job,
+        options: ImageModeOptions,
+    ) -> Result<Option<Snapshot>> {
+        let (body, fetch) = self.fetch_bytes(&job.url).await?;
+
+        // Decoding and encoding large images would stall the async runtime
+        let (fingerprint, png) = tokio::task::spawn_blocking(move || -> Result<_> {
+            let image = image::load_from_memory(&body)?;
+
+            Ok((ImageFingerprint::from_image(&image), encode_png(&image)?))
+        })
+        .await??;
+
+        let prev_snapshot = self.db.snapshots_get_latest(&job.id).await?;
+        let prev_fingerprint = prev_snapshot
+            .as_ref()
+            .and_then(|snap| ImageFingerprint::from_summary(&snap.data));
+
+        if let Some(prev) = &prev_fingerprint {
+            let min_distance = options
+                .min_perceptual_distance
+                .unwrap_or(DEFAULT_MIN_PERCEPTUAL_DISTANCE);
+
+            if fingerprint.perceptual_distance(prev) < min_distance
+                || fingerprint.difference_percent(prev) < options.threshold
+            {
+                return Ok(None);
+            }
+        }
+
+        let data = InsertableSnapshot {
+            job_id: job.id.clone(),
+            data: fingerprint.to_summary(prev_fingerprint.as_ref()),
+            value: None,
+            raw_data: Some(base64::encode(&png)),
+            created_at: Utc::now().into(),
+            fetch: Some(fetch),
+        };
+        let new_snapshot = self.db.snapshots_add(data).await?;
+
+        let mut attachments = vec![Attachment {
+            filename: String::from("current.png"),
+            content_type: String::from("image/png"),
+            data: png,
+        }];
+
+        // Snapshots from before full images were stored only have a thumbnail
+        let prev_png = prev_snapshot
+            .as_ref()
+            .and_then(|snap| snap.raw_data.as_ref())
+            .and_then(|raw_data| base64::decode(raw_data).ok());
+        if let Some(prev_png) = prev_png {
+            attachments.push(Attachment {
+                filename: String::from("previous.png"),
+                content_type: String::from("image/png"),
+                data: prev_png,
+            });
+        }
+
+        if let Some(prev) = &prev_fingerprint {
+            attachments.push(Attachment {
+                filename: String::from("diff.png"),
+                content_type: String::from("image/png"),
+                data: encode_png(&DynamicImage::ImageRgb8(fingerprint.diff_image(prev)))?,
+            });
+        }
+
+        self.send_notifications(job, &prev_snapshot, &new_snapshot, &attachments)
+            .await;
+
+        Ok(Some(new_snapshot))
+    }
+
+    /// Checks whether the number in the job's filtered content changed. Every changed
+    /// value is recorded, but notifications are only sent if the job's number trigger
+    /// (and all of its other triggers) are met.
+    pub async fn run_number_check_for_job(
+        &self,
+        job: &Job,
+        options: NumberModeOptions,
+    ) -> Result<Option<Snapshot>> {
+        let (website_dom, fetch) = self.fetch_website(&job.url).await?;
+        let filtered_dom = self.apply_filters(website_dom, &job.filters).await?;
+
+        let value = parse_number(&filtered_dom, options.decimal_separator)
+            .ok_or(WebmonitorError::NumberNotFound)?;
+
+        let prev_snapshot = self.db.snapshots_get_latest(&job.id).await?;
+        let prev_value = prev_snapshot.as_ref().and_then(|snap| snap.value);
+
+        if prev_value == Some(value) {
+            return Ok(None);
+        }
+
+        let data = InsertableSnapshot {
+            job_id: job.id.clone(),
+            data: value.to_string(),
+            value: Some(value),
+            raw_data: None,
+            created_at: Utc::now().into(),
+            fetch: Some(fetch),
+        };
+        let new_snapshot = self.db.snapshots_add(data).await?;
+
+        let should_notify = match prev_value {
+            Some(prev_value) => number_trigger_met(&options.trigger, prev_value, value),
+            None => true,
+        };
+
+        if should_notify && self.triggers_met(job, &prev_snapshot, &new_snapshot)? {
+            self.send_notifications(job, &prev_snapshot, &new_snapshot, &[])
+                .await;
+        }
+
+        Ok(Some(new_snapshot))
+    }
+
+    /// Fetches the content at the given url as text, extracting the text of PDF
+    /// documents if necessary, along with metadata about the request
+    async fn fetch_website(&self, url: &str) -> Result<(String, FetchMetadata)> {
+        let started = Instant::now();
+        let response = reqwest::get(url).await?;
+        let mut fetch = response_metadata(&response);
+
+        let is_pdf = PdfTextFilter::is_pdf_response(&response);
+        let encoding = response_encoding(&response);
+
+        let body = response.bytes().await?;
+        fetch.byte_size = body.len() as u64;
+
+        let content = if is_pdf {
+            PdfTextFilter.extract(&body)?
+        } else {
+            encoding.decode(&body).0.into_owned()
+        };
+
+        fetch.duration_millis = started.elapsed().as_millis() as u64;
+
+        Ok((content, fetch))
+    }
+
+    /// Fetches the raw body at the given url, along with metadata about the request
+    async fn fetch_bytes(&self, url: &str) -> Result<(Bytes, FetchMetadata)> {
+        let started = Instant::now();
+        let response = reqwest::get(url).await?;
+        let mut fetch = response_metadata(&response);
+
+        let body = response.bytes().await?;
+        fetch.byte_size = body.len() as u64;
+        fetch.duration_millis = started.elapsed().as_millis() as u64;
+
+        Ok((body, fetch))
+    }
+
+    async fn send_notifications(
+        &self,
+        job: &Job,
+        prev_snapshot: &Option<Snapshot>,
+        new_snapshot: &Snapshot,
+        attachments: &[Attachment],
+    ) {
+        let notifications = &job.notifications;
+        future::join_all(notifications.iter().map(|notification| async move {
+            match notification {
+                Notification::Discord(options) => {
+                    DiscordNotification::with_options(options.clone())
+                        .send_with_attachments(job, prev_snapshot, new_snapshot, attachments)
+                        .await
+                }
+                Notification::Email(options) => {
+                    EmailNotification::with_options(options.clone())
+                        .send_with_attachments(job, prev_snapshot, new_snapshot, attachments)
+                        .await
                 }
-                Filter::XPathFilter(options) => {
-                    XPathFilter::with_options(options.clone()).apply(filtered_dom)
+            };
+        }))
+        .await;
+    }
+
+    fn triggers_met(
+        &self,
+        job: &Job,
+        prev_snapshot: &Option<Snapshot>,
+        new_snapshot: &Snapshot,
+    ) -> Result<bool> {
+        triggers_met(
+            &job.triggers,
+            prev_snapshot.as_ref().map(|snap| snap.data.as_str()),
+            &new_snapshot.data,
+        )
+    }
+
+    /// Applies the filter chain on a blocking thread, as parsing documents
+    /// and running scripts would otherwise stall the async runtime
+    async fn apply_filters(&self, dom: String, filters: &[Filter]) -> Result<String> {
+        let filters = filters.to_vec();
+
+        tokio::task::spawn_blocking(move || apply_filter_chain(dom, &filters)).await?
+    }
+
+    fn dom_has_changed(&self, detector: &ChangeDetector, dom: &str, other_dom: &str) -> bool {
+        match detector {
+            ChangeDetector::ExactDetector => ExactDetector.has_changed(dom, other_dom),
+            ChangeDetector::IgnoreWhitespaceDetector => {
+                IgnoreWhitespaceDetector.has_changed(dom, other_dom)
+            }
+            ChangeDetector::ThresholdDetector(options) => {
+                ThresholdDetector::with_options(options.clone()).has_changed(dom, other_dom)
+            }
+            ChangeDetector::AdditionsDetector => AdditionsDetector.has_changed(dom, other_dom),
+            ChangeDetector::RemovalsDetector => RemovalsDetector.has_changed(dom, other_dom),
+        }
+    }
+
+    /// Compares two file summaries by hash and size only,
+    /// so that changing headers alone don't count as a change
+    fn file_has_changed(&self, summary: &str, other_summary: &str) -> bool {
+        let digest = |summary: &str| -> Vec<String> {
+            summary
+                .lines()
+                .filter(|line| line.starts_with("sha256: ") || line.starts_with("size: "))
+                .map(String::from)
+                .collect()
+        };
+
+        digest(summary) != digest(other_summary)
+    }
+
+    fn format_feed_entry(&self, entry: &Entry) -> String {
+        let title = match &entry.title {
+            Some(title) => title.content.as_str(),
+            None => "(untitled)",
+        };
+
+        match entry.links.first() {
+            Some(link) => format!("{} ({})", title, link.href),
+            None => String::from(title),
+        }
+    }
+}
+
+/// Collects the metadata of a response that's known before reading its body.
+/// The body's size and the duration of the request are filled in by the caller.
+fn response_metadata(response: &Response) -> FetchMetadata {
+    let headers: BTreeMap<String, String> = [CONTENT_TYPE, ETAG, LAST_MODIFIED, CACHE_CONTROL]
+        .iter()
+        .filter_map(|name| {
+            let value = response.headers().get(name)?.to_str().ok()?;
+            Some((name.to_string(), String::from(value)))
+        })
+        .collect();
+
+    FetchMetadata {
+        status: response.status().as_u16(),
+        final_url: response.url().to_string(),
+        headers,
+        duration_millis: 0,
+        byte_size: 0,
+    }
+}
+
+/// Returns the text encoding given by the charset of the response's content type,
+/// falling back to UTF-8 like reqwest's text() does
+fn response_encoding(response: &Response) -> &'static Encoding {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|content_type| {
+            content_type.split(';').find_map(|param| {
+                let (name, value) = param.trim().split_once('=')?;
+                if name.trim().eq_ignore_ascii_case("charset") {
+                    Encoding::for_label(value.trim().trim_matches('"').as_bytes())
+                } else {
+                    None
                 }
-                Filter::Html2TextFilter => Html2TextFilter.apply(filtered_dom),
             })
+        })
+        .unwrap_or(UTF_8)
+}
+
+fn apply_filter_chain(dom: String, filters: &[Filter]) -> Result<String> {
+    filters
+        .iter()
+        .try_fold(dom, |filtered_dom, filter| match filter {
+            Filter::CSSFilter(options) => {
+                CSSFilter::with_options(options.clone()).apply(filtered_dom)
+            }
+            Filter::XPathFilter(options) => {
+                XPathFilter::with_options(options.clone()).apply(filtered_dom)
+            }
+            Filter::Html2TextFilter => Html2TextFilter.apply(filtered_dom),
+            Filter::Html2MarkdownFilter => Html2MarkdownFilter.apply(filtered_dom),
+            Filter::JsonPathFilter(options) => {
+                JsonPathFilter::with_options(options.clone()).apply(filtered_dom)
+            }
+            Filter::RegexExtractFilter(options) => {
+                RegexExtractFilter::with_options(options.clone())?.apply(filtered_dom)
+            }
+            Filter::RegexReplaceFilter(options) => {
+                RegexReplaceFilter::with_options(options.clone())?.apply(filtered_dom)
+            }
+            Filter::TrimLinesFilter => TrimLinesFilter.apply(filtered_dom),
+            Filter::CollapseWhitespaceFilter => CollapseWhitespaceFilter.apply(filtered_dom),
+            Filter::RemoveBlankLinesFilter => RemoveBlankLinesFilter.apply(filtered_dom),
+            Filter::SortLinesFilter => SortLinesFilter.apply(filtered_dom),
+            Filter::DeduplicateLinesFilter => DeduplicateLinesFilter.apply(filtered_dom),
+            Filter::CaseFoldFilter => CaseFoldFilter.apply(filtered_dom),
+            Filter::UnicodeNormalizeFilter(options) => {
+                UnicodeNormalizeFilter::with_options(options.clone()).apply(filtered_dom)
+            }
+            Filter::ScriptFilter(options) => {
+                ScriptFilter::with_options(options.clone())?.apply(filtered_dom)
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{
+        model::{InsertableJob, RegexExtractFilterOptions},
+        repository::MemoryRepository,
+    };
+
+    fn job(mode: JobMode) -> InsertableJob {
+        InsertableJob {
+            name: String::from("Shop"),
+            url: String::from("https://example.com"),
+            show_diff: false,
+            mode,
+            change_detector: ChangeDetector::ExactDetector,
+            triggers: vec![],
+            confirmation: None,
+            ignore_recent_snapshots: 0,
+            retention: None,
+            store_raw: true,
+            store_search_text: false,
+            interval: 60,
+            filters: vec![],
+            notifications: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn recompute_history_reapplies_filters_to_raw_content() {
+        let db: Arc<dyn Repository> = Arc::new(MemoryRepository::new());
+        let job = db.jobs_add(job(JobMode::Website)).await.unwrap();
+
+        // More snapshots than fit on a page, whose price changes every 50 snapshots
+        // while the visitor count changes with every one of them
+        let count = RECOMPUTE_PAGE_SIZE as i64 * 2 + 50;
+        for index in 0..count {
+            let raw_data = format!("Visitors: {}\nPrice: {}", index, index / 50);
+            db.snapshots_add(InsertableSnapshot {
+                job_id: job.id.clone(),
+                data: raw_data.clone(),
+                value: None,
+                raw_data: if index == 10 { None } else { Some(raw_data) },
+                created_at: DateTime::from(Utc.timestamp_millis(index * 1000)),
+                fetch: None,
+            })
+            .await
+            .unwrap();
+        }
+
+        let filters = vec![Filter::RegexExtractFilter(RegexExtractFilterOptions {
+            pattern: String::from(r"Price: \d+"),
+            group: None,
+        })];
+        let history = WebsiteMonitor::new(db)
+            .recompute_history(&job, &filters)
+            .await
+            .unwrap();
+
+        // All snapshots are recomputed from oldest to newest, except the one without raw content
+        let recomputed_at: Vec<i64> = history
+            .iter()
+            .map(|snapshot| snapshot.created_at.timestamp_millis() / 1000)
+            .collect();
+        let expected_at: Vec<i64> = (0..count).filter(|index| *index != 10).collect();
+        assert_eq!(recomputed_at, expected_at);
+        assert_eq!(history[0].data, "Price: 0");
+        assert_eq!(history[history.len() - 1].data, "Price: 4");
+
+        let changed_at: Vec<i64> = history
+            .iter()
+            .filter(|snapshot| snapshot.changed)
+            .map(|snapshot| snapshot.created_at.timestamp_millis() / 1000)
+            .collect();
+        assert_eq!(changed_at, vec![0, 50, 100, 150, 200]);
     }
 
-    fn dom_has_changed(&self, dom: &str, other_dom: &str) -> bool {
-        dom != other_dom
+    #[tokio::test]
+    async fn recompute_history_rejects_other_modes() {
+        let db: Arc<dyn Repository> = Arc::new(MemoryRepository::new());
+        let job = db.jobs_add(job(JobMode::Feed)).await.unwrap();
+
+        let result = WebsiteMonitor::new(db).recompute_history(&job, &[]).await;
+
+        assert!(matches!(
+            result,
+            Err(WebmonitorError::UnsupportedJobMode(_))
+        ));
     }
 }
@@ -1,9 +1,14 @@
-use crate::model::{DiscordNotificationOptions, Job, Snapshot};
+use crate::{
+    diffing::render_diff,
+    imaging::ImageFingerprint,
+    model::{DiscordNotificationOptions, Job, JobMode, Snapshot},
+    numbers::describe_change,
+};
 use async_trait::async_trait;
+use reqwest::multipart::{Form, Part};
 use serde_json::{json, Value};
-use similar::{ChangeTag, TextDiff};
 
-use super::NotificationSend;
+use super::{Attachment, NotificationSend};
 
 pub struct DiscordNotification {
     options: DiscordNotificationOptions,
@@ -18,10 +23,37 @@ impl DiscordNotification {
 #[async_trait]
 impl NotificationSend for DiscordNotification {
     async fn send(&self, job: &Job, prev_snapshot: &Option<Snapshot>, new_snapshot: &Snapshot) {
+        self.send_with_attachments(job, prev_snapshot, new_snapshot, &[])
+            .await
+    }
+
+    async fn send_with_attachments(
+        &self,
+        job: &Job,
+        prev_snapshot: &Option<Snapshot>,
+        new_snapshot: &Snapshot,
+        attachments: &[Attachment],
+    ) {
         let mut embed_fields: Vec<Value> = Vec::new();
 
-        if job.show_diff {
-            let diff = TextDiff::from_lines(
+        if job.mode == JobMode::Feed {
+            embed_fields.push(json!(
+                {
+                    "name": "New items:",
+                    "value": &new_snapshot.data
+                }
+            ));
+        } else if let (JobMode::Number(_), Some(value)) = (job.mode, new_snapshot.value) {
+            let prev_value = prev_snapshot.as_ref().and_then(|snap| snap.value);
+
+            embed_fields.push(json!(
+                {
+                    "name": "Value:",
+                    "value": describe_change(prev_value, value)
+                }
+            ));
+        } else if job.show_diff && job.mode == JobMode::Website {
+            let diff_content = render_diff(
                 match prev_snapshot {
                     Some(snap) => snap.data.as_str(),
                     None => "",
@@ -29,21 +61,6 @@ impl NotificationSend for DiscordNotification {
                 &new_snapshot.data,
             );
 
-            let diff_content = diff
-                .iter_all_changes()
-                .fold(String::from(""), |mut acc, change| {
-                    let sign = match change.tag() {
-                        ChangeTag::Delete => "- ",
-                        ChangeTag::Insert => "+ ",
-                        ChangeTag::Equal => "  ",
-                    };
-
-                    acc.push_str(sign);
-                    acc.push_str(&change.to_string());
-
-                    acc
-                });
-
             embed_fields.push(json!(
                 {
                     "name": "Diff:",
@@ -51,18 +68,28 @@ impl NotificationSend for DiscordNotification {
                 }
             ));
         } else {
+            // Summaries of files and images aren't markup, so they don't get highlighted
+            let language = match job.mode {
+                JobMode::Website => "html",
+                _ => "",
+            };
+            let display = |snapshot: &Snapshot| match job.mode {
+                JobMode::Image(_) => ImageFingerprint::describe_summary(&snapshot.data),
+                _ => snapshot.data.clone(),
+            };
+
             if let Some(snap) = prev_snapshot {
                 embed_fields.push(json!(
                     {
                         "name": "Previous:",
-                        "value": format!("```html\n{}```", &snap.data)
+                        "value": format!("```{}\n{}```", language, display(snap))
                     }
                 ));
             }
             embed_fields.push(json!(
                 {
                     "name": "New:",
-                    "value": format!("```html\n{}```", &new_snapshot.data)
+                    "value": format!("```{}\n{}```", language, display(new_snapshot))
                 }
             ));
         }
@@ -76,15 +103,54 @@ impl NotificationSend for DiscordNotification {
             ]
         });
 
+        if let Some(fetch) = &new_snapshot.fetch {
+            request_body["embeds"][0]["footer"] = json!({
+                "text": format!(
+                    "HTTP {} · {} bytes in {} ms · {}",
+                    fetch.status, fetch.byte_size, fetch.duration_millis, fetch.final_url
+                )
+            });
+        }
+
         if let Some(mentions) = &self.options.user_mentions {
             request_body["content"] = json!(mentions);
         }
 
+        if attachments.is_empty() {
+            let client = reqwest::Client::new();
+            let _ = client
+                .post(&self.options.webhook_url)
+                .header("Content-type", "application/json")
+                .body(request_body.to_string())
+                .send()
+                .await;
+
+            return;
+        }
+
+        // Show the first attached image right in the embed
+        if let Some(attachment) = attachments.first() {
+            if attachment.content_type.starts_with("image/") {
+                request_body["embeds"][0]["image"] =
+                    json!({ "url": format!("attachment://{}", attachment.filename) });
+            }
+        }
+
+        let mut form = Form::new().text("payload_json", request_body.to_string());
+        for (index, attachment) in attachments.iter().enumerate() {
+            let part = Part::bytes(attachment.data.clone())
+                .file_name(attachment.filename.clone())
+                .mime_str(attachment.content_type.as_str());
+
+            if let Ok(part) = part {
+                form = form.part(format!("file{}", index), part);
+            }
+        }
+
         let client = reqwest::Client::new();
         let _ = client
             .post(&self.options.webhook_url)
-            .header("Content-type", "application/json")
-            .body(request_body.to_string())
+            .multipart(form)
             .send()
             .await;
     }
@@ -4,6 +4,7 @@ use async_trait::async_trait;
 use super::NotificationSend;
 
 pub struct EmailNotification {
+    #[allow(dead_code)]
     options: EmailNotificationOptions,
 }
 
@@ -15,5 +16,5 @@ impl EmailNotification {
 
 #[async_trait]
 impl NotificationSend for EmailNotification {
-    async fn send(&self, job: &Job, prev_snapshot: &Option<Snapshot>, new_snapshot: &Snapshot) {}
+    async fn send(&self, _job: &Job, _prev_snapshot: &Option<Snapshot>, _new_snapshot: &Snapshot) {}
 }
@@ -8,7 +8,27 @@ pub use self::discord::*;
 mod email;
 pub use self::email::*;
 
+/// A file that's sent along with a notification, like the images of an image job
+#[derive(Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
 #[async_trait]
 pub trait NotificationSend {
     async fn send(&self, job: &Job, prev_snapshot: &Option<Snapshot>, new_snapshot: &Snapshot);
+
+    /// Sends the notification along with the given attachments.
+    /// Notifiers that don't support attachments send the notification without them.
+    async fn send_with_attachments(
+        &self,
+        job: &Job,
+        prev_snapshot: &Option<Snapshot>,
+        new_snapshot: &Snapshot,
+        _attachments: &[Attachment],
+    ) {
+        self.send(job, prev_snapshot, new_snapshot).await
+    }
 }
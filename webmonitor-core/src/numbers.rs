@@ -0,0 +1,154 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::model::NumberTrigger;
+
+// Digits with an optional sign, thousands separators and decimal part. Spaces and apostrophes
+// only count as thousands separators in front of a group of exactly three digits,
+// so separate numbers like the ones in "3 items 12" aren't merged.
+static NUMBER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"-?\d+(?:[ '\u{a0}\u{202f}]\d{3}\b|[.,]\d+)*").unwrap());
+
+/// Parses the first number found in the given text, like the price in "1.299,00 €".
+/// If no decimal separator is given, it's guessed from the number itself: when both
+/// '.' and ',' are used, the last one is the decimal separator, and a single separator
+/// followed by exactly three digits (like in "1,299") is treated as a thousands separator.
+pub fn parse_number(text: &str, decimal_separator: Option<char>) -> Option<f64> {
+    let found = NUMBER_REGEX.find(text)?.as_str();
+
+    let digits: String = found
+        .trim_end_matches(|c: char| !c.is_ascii_digit())
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\'')
+        .collect();
+
+    let decimal_separator = decimal_separator.or_else(|| guess_decimal_separator(&digits));
+
+    let normalized: String = digits
+        .chars()
+        .filter_map(|c| match c {
+            '.' | ',' if Some(c) == decimal_separator => Some('.'),
+            '.' | ',' => None,
+            _ => Some(c),
+        })
+        .collect();
+
+    normalized.parse().ok()
+}
+
+fn guess_decimal_separator(digits: &str) -> Option<char> {
+    match (digits.rfind('.'), digits.rfind(',')) {
+        (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+        (Some(index), None) | (None, Some(index)) => {
+            let separator = digits[index..].chars().next()?;
+            let is_single = digits.matches(separator).count() == 1;
+            let decimals = digits.len() - index - 1;
+
+            if is_single && decimals != 3 {
+                Some(separator)
+            } else {
+                None
+            }
+        }
+        (None, None) => None,
+    }
+}
+
+/// Returns whether the change from the previous to the new value should be notified about
+pub fn number_trigger_met(trigger: &NumberTrigger, prev: f64, new: f64) -> bool {
+    match *trigger {
+        NumberTrigger::AnyChange => prev != new,
+        NumberTrigger::DropsBelow(limit) => new < limit && prev >= limit,
+        NumberTrigger::RisesAbove(limit) => new > limit && prev <= limit,
+        NumberTrigger::DropsByPercent(percent) => change_percent(prev, new) <= -percent,
+        NumberTrigger::RisesByPercent(percent) => change_percent(prev, new) >= percent,
+    }
+}
+
+fn change_percent(prev: f64, new: f64) -> f64 {
+    if prev == 0.0 {
+        return 0.0;
+    }
+
+    (new - prev) / prev.abs() * 100.0
+}
+
+/// Describes the change of a value for notifications, like "1299 → 1199 (-100, -7.7%)"
+pub fn describe_change(prev: Option<f64>, new: f64) -> String {
+    match prev {
+        Some(prev) => format!(
+            "{} → {} ({:+}, {:+.1}%)",
+            prev,
+            new,
+            new - prev,
+            change_percent(prev, new)
+        ),
+        None => format!("{}", new),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_numbers_with_guessed_decimal_separator() {
+        assert_eq!(parse_number("1.299,00 €", None), Some(1299.0));
+        assert_eq!(parse_number("$1,299.99", None), Some(1299.99));
+        assert_eq!(parse_number("1'234.50", None), Some(1234.5));
+        assert_eq!(parse_number("1 234 567 visitors", None), Some(1234567.0));
+        assert_eq!(parse_number("Price: -12,5", None), Some(-12.5));
+        assert_eq!(parse_number("1,299", None), Some(1299.0));
+        assert_eq!(parse_number("v2.0 released", None), Some(2.0));
+    }
+
+    #[test]
+    fn parses_numbers_with_given_decimal_separator() {
+        assert_eq!(parse_number("1,299", Some(',')), Some(1.299));
+        assert_eq!(parse_number("1.299", Some(',')), Some(1299.0));
+    }
+
+    #[test]
+    fn returns_none_without_number() {
+        assert_eq!(parse_number("sold out", None), None);
+    }
+
+    #[test]
+    fn any_change_trigger() {
+        assert!(number_trigger_met(&NumberTrigger::AnyChange, 1.0, 2.0));
+        assert!(!number_trigger_met(&NumberTrigger::AnyChange, 2.0, 2.0));
+    }
+
+    #[test]
+    fn limit_triggers_only_fire_when_crossing_the_limit() {
+        let drops_below = NumberTrigger::DropsBelow(100.0);
+        assert!(number_trigger_met(&drops_below, 120.0, 90.0));
+        assert!(number_trigger_met(&drops_below, 100.0, 99.0));
+        assert!(!number_trigger_met(&drops_below, 90.0, 80.0));
+        assert!(!number_trigger_met(&drops_below, 120.0, 100.0));
+
+        let rises_above = NumberTrigger::RisesAbove(100.0);
+        assert!(number_trigger_met(&rises_above, 90.0, 120.0));
+        assert!(!number_trigger_met(&rises_above, 120.0, 130.0));
+        assert!(!number_trigger_met(&rises_above, 90.0, 100.0));
+    }
+
+    #[test]
+    fn percent_triggers() {
+        let drops_by = NumberTrigger::DropsByPercent(10.0);
+        assert!(number_trigger_met(&drops_by, 100.0, 90.0));
+        assert!(!number_trigger_met(&drops_by, 100.0, 95.0));
+        assert!(!number_trigger_met(&drops_by, 100.0, 120.0));
+
+        let rises_by = NumberTrigger::RisesByPercent(10.0);
+        assert!(number_trigger_met(&rises_by, 100.0, 110.0));
+        assert!(number_trigger_met(&rises_by, -100.0, -50.0));
+        assert!(!number_trigger_met(&rises_by, 0.0, 50.0));
+    }
+
+    #[test]
+    fn parses_separate_numbers_separately() {
+        assert_eq!(parse_number("3 items 12", None), Some(3.0));
+        assert_eq!(parse_number("12 34", None), Some(12.0));
+    }
+}
@@ -0,0 +1,57 @@
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, WebmonitorError};
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// The data of a snapshot as it's stored by the persistent repositories:
+/// zstd-compressed and addressed by the SHA-256 hash of the uncompressed data,
+/// so identical contents of any number of snapshots are only stored once.
+pub struct SnapshotBody {
+    pub hash: String,
+    pub compressed: Vec<u8>,
+}
+
+impl SnapshotBody {
+    pub fn compress(data: &str) -> Result<Self> {
+        Ok(Self {
+            hash: body_hash(data),
+            compressed: zstd::encode_all(data.as_bytes(), COMPRESSION_LEVEL)?,
+        })
+    }
+}
+
+pub fn body_hash(data: &str) -> String {
+    format!("{:x}", Sha256::digest(data.as_bytes()))
+}
+
+pub fn decompress_body(compressed: &[u8]) -> io::Result<String> {
+    let data = zstd::decode_all(compressed)?;
+
+    String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Returns the data of a snapshot row of the SQL databases, which is either the joined body,
+/// or still stored uncompressed in the snapshot row if it wasn't migrated yet
+pub fn row_data(compressed: Option<&[u8]>, legacy_data: Option<String>) -> io::Result<String> {
+    match (compressed, legacy_data) {
+        (Some(compressed), _) => decompress_body(compressed),
+        (None, Some(data)) => Ok(data),
+        (None, None) => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "The stored data of the snapshot is missing",
+        )),
+    }
+}
+
+/// Returns the raw data of a snapshot row of the SQL databases,
+/// which must have been joined if the snapshot references a raw body
+pub fn row_raw_data(hash: Option<String>, compressed: Option<&[u8]>) -> Result<Option<String>> {
+    match (hash, compressed) {
+        (Some(_), Some(compressed)) => Ok(Some(decompress_body(compressed)?)),
+        (Some(hash), None) => Err(WebmonitorError::SnapshotBodyMissing(hash)),
+        (None, _) => Ok(None),
+    }
+}
@@ -0,0 +1,191 @@
+use mongodb::bson::DateTime;
+
+use crate::{
+    error::{Result, WebmonitorError},
+    model::{Snapshot, SnapshotPage, SortOrder},
+};
+
+/// A position in a job's snapshot history, which is ordered by creation time and then by id.
+/// Handed out as `<created_at millis>-<id>`, so pages stay stable while new snapshots are added.
+/// Ids never contain a `-`, so the cursor is split at the last one,
+/// which keeps timestamps before 1970 (with a leading `-`) intact.
+pub struct SnapshotCursor {
+    pub created_at: i64,
+    pub id: String,
+}
+
+impl SnapshotCursor {
+    pub fn of(snapshot: &Snapshot) -> Self {
+        Self {
+            created_at: snapshot.created_at.timestamp_millis(),
+            id: snapshot.id.clone(),
+        }
+    }
+
+    pub fn parse(cursor: &str) -> Result<Self> {
+        cursor
+            .rsplit_once('-')
+            .filter(|(_, id)| !id.is_empty())
+            .and_then(|(created_at, id)| {
+                Some(Self {
+                    created_at: created_at.parse().ok()?,
+                    id: id.to_string(),
+                })
+            })
+            .ok_or_else(|| WebmonitorError::InvalidCursor(cursor.to_string()))
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}-{}", self.created_at, self.id)
+    }
+
+    /// Returns whether the snapshot comes after the cursor in the given order
+    pub fn precedes(&self, snapshot: &Snapshot, order: SortOrder) -> bool {
+        let position = (snapshot.created_at.timestamp_millis(), snapshot.id.as_str());
+        let cursor = (self.created_at, self.id.as_str());
+
+        match order {
+            SortOrder::NewestFirst => position < cursor,
+            SortOrder::OldestFirst => position > cursor,
+        }
+    }
+}
+
+/// Returns whether the snapshot was created at or after `after` and before `before`
+pub fn in_range(snapshot: &Snapshot, after: Option<DateTime>, before: Option<DateTime>) -> bool {
+    let created_at = snapshot.created_at.timestamp_millis();
+
+    after.is_none_or(|after| created_at >= after.timestamp_millis())
+        && before.is_none_or(|before| created_at < before.timestamp_millis())
+}
+
+pub fn sort_snapshots(snapshots: &mut [Snapshot], order: SortOrder) {
+    snapshots.sort_by(|a, b| {
+        let ordering = a
+            .created_at
+            .timestamp_millis()
+            .cmp(&b.created_at.timestamp_millis())
+            .then_with(|| a.id.cmp(&b.id));
+
+        match order {
+            SortOrder::NewestFirst => ordering.reverse(),
+            SortOrder::OldestFirst => ordering,
+        }
+    });
+}
+
+/// The number of snapshots to fetch for a page: one more than the limit,
+/// which tells whether there's a next page
+pub fn fetch_limit(limit: Option<u32>) -> Option<i64> {
+    limit.map(|limit| limit as i64 + 1)
+}
+
+/// Turns snapshots that were fetched with fetch_limit into a page
+pub fn page_from_snapshots(mut snapshots: Vec<Snapshot>, limit: Option<u32>) -> SnapshotPage {
+    let next_cursor = match limit {
+        Some(limit) if snapshots.len() > limit as usize => {
+            snapshots.truncate(limit as usize);
+            snapshots
+                .last()
+                .map(|snapshot| SnapshotCursor::of(snapshot).encode())
+        }
+        _ => None,
+    };
+
+    SnapshotPage {
+        snapshots,
+        next_cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn snapshot(id: &str, created_at: i64) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            job_id: String::from("job"),
+            data: String::new(),
+            value: None,
+            raw_data: None,
+            created_at: DateTime::from(Utc.timestamp_millis(created_at)),
+            fetch: None,
+        }
+    }
+
+    fn ids(page: &SnapshotPage) -> Vec<&str> {
+        page.snapshots
+            .iter()
+            .map(|snapshot| snapshot.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parses_encoded_cursors() {
+        let cursor = SnapshotCursor::parse("1620000000000-6097a1f4b1e1d7a1c8f2e3d4").unwrap();
+
+        assert_eq!(cursor.created_at, 1620000000000);
+        assert_eq!(cursor.id, "6097a1f4b1e1d7a1c8f2e3d4");
+        assert_eq!(cursor.encode(), "1620000000000-6097a1f4b1e1d7a1c8f2e3d4");
+    }
+
+    #[test]
+    fn parses_cursors_before_1970() {
+        let cursor = SnapshotCursor::parse("-86400000-abc").unwrap();
+
+        assert_eq!(cursor.created_at, -86400000);
+        assert_eq!(cursor.id, "abc");
+    }
+
+    #[test]
+    fn rejects_invalid_cursors() {
+        for cursor in &["", "abc", "5-", "x-abc", "-5"] {
+            assert!(
+                SnapshotCursor::parse(cursor).is_err(),
+                "{:?} was accepted",
+                cursor
+            );
+        }
+    }
+
+    #[test]
+    fn cursors_precede_the_following_snapshots() {
+        let cursor = SnapshotCursor::parse("2000-b").unwrap();
+
+        assert!(cursor.precedes(&snapshot("a", 2000), SortOrder::NewestFirst));
+        assert!(cursor.precedes(&snapshot("a", 1000), SortOrder::NewestFirst));
+        assert!(!cursor.precedes(&snapshot("b", 2000), SortOrder::NewestFirst));
+        assert!(cursor.precedes(&snapshot("c", 2000), SortOrder::OldestFirst));
+        assert!(!cursor.precedes(&snapshot("a", 3000), SortOrder::NewestFirst));
+    }
+
+    #[test]
+    fn full_pages_have_a_cursor_to_the_next_page() {
+        let snapshots = vec![
+            snapshot("c", 3000),
+            snapshot("b", 2000),
+            snapshot("a", 2000),
+        ];
+
+        let page = page_from_snapshots(snapshots, Some(2));
+
+        assert_eq!(ids(&page), vec!["c", "b"]);
+        assert_eq!(page.next_cursor.as_deref(), Some("2000-b"));
+    }
+
+    #[test]
+    fn last_pages_have_no_cursor() {
+        let snapshots = vec![snapshot("c", 3000), snapshot("b", 2000)];
+
+        let page = page_from_snapshots(snapshots.clone(), Some(2));
+        assert_eq!(ids(&page), vec!["c", "b"]);
+        assert_eq!(page.next_cursor, None);
+
+        let page = page_from_snapshots(snapshots, None);
+        assert_eq!(ids(&page), vec!["c", "b"]);
+        assert_eq!(page.next_cursor, None);
+    }
+}
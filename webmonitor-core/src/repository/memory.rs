@@ -0,0 +1,344 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::{
+    error::Result,
+    model::{
+        CheckRun, FeedItem, InsertableCheckRun, InsertableJob, InsertableSnapshot, Job,
+        PruneReport, Snapshot, SnapshotHeader, SnapshotPage, SnapshotQuery, SortOrder,
+    },
+};
+
+use super::{
+    history::{fetch_limit, in_range, page_from_snapshots, sort_snapshots, SnapshotCursor},
+    Repository,
+};
+
+#[derive(Default)]
+struct MemoryData {
+    jobs: Vec<Job>,
+    snapshots: Vec<Snapshot>,
+    feed_items: Vec<FeedItem>,
+    check_runs: Vec<CheckRun>,
+}
+
+/// Keeps everything in memory, so nothing persists across restarts.
+/// Useful for tests and for trying out jobs without setting up a database.
+///
+/// Items are kept in insertion order, and ids are generated
+/// in the same format as MongoDB's ObjectIds. Snapshots and check runs are still sorted
+/// by their time when queried, like the other repositories do.
+#[derive(Default)]
+pub struct MemoryRepository {
+    data: Mutex<MemoryData>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a page of the snapshots that are accepted by the filter and match the query
+    fn query_snapshots<F>(&self, query: &SnapshotQuery, filter: F) -> Result<SnapshotPage>
+    where
+        F: Fn(&Snapshot) -> bool,
+    {
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(SnapshotCursor::parse)
+            .transpose()?;
+
+        let mut snapshots: Vec<Snapshot> = self
+            .data
+            .lock()
+            .unwrap()
+            .snapshots
+            .iter()
+            .filter(|snapshot| {
+                filter(snapshot)
+                    && in_range(snapshot, query.after, query.before)
+                    && cursor
+                        .as_ref()
+                        .is_none_or(|cursor| cursor.precedes(snapshot, query.order))
+            })
+            .cloned()
+            .collect();
+        sort_snapshots(&mut snapshots, query.order);
+
+        if let Some(limit) = fetch_limit(query.limit) {
+            snapshots.truncate(limit as usize);
+        }
+
+        Ok(page_from_snapshots(snapshots, query.limit))
+    }
+}
+
+#[async_trait]
+impl Repository for MemoryRepository {
+    async fn jobs_get_all(&self) -> Result<Vec<Job>> {
+        Ok(self.data.lock().unwrap().jobs.clone())
+    }
+
+    async fn jobs_get_one(&self, id: &str) -> Result<Option<Job>> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data.jobs.iter().find(|job| job.id == id).cloned())
+    }
+
+    async fn jobs_add(&self, job: InsertableJob) -> Result<Job> {
+        let job = Job {
+            id: ObjectId::new().to_hex(),
+            name: job.name,
+            url: job.url,
+            show_diff: job.show_diff,
+            mode: job.mode,
+            change_detector: job.change_detector,
+            triggers: job.triggers,
+            confirmation: job.confirmation,
+            ignore_recent_snapshots: job.ignore_recent_snapshots,
+            retention: job.retention,
+            store_raw: job.store_raw,
+            store_search_text: job.store_search_text,
+            interval: job.interval,
+            filters: job.filters,
+            notifications: job.notifications,
+        };
+
+        self.data.lock().unwrap().jobs.push(job.clone());
+
+        Ok(job)
+    }
+
+    async fn jobs_update(&self, job: Job) -> Result<Job> {
+        let mut data = self.data.lock().unwrap();
+
+        if let Some(stored) = data.jobs.iter_mut().find(|stored| stored.id == job.id) {
+            *stored = job.clone();
+        }
+
+        Ok(job)
+    }
+
+    async fn jobs_delete(&self, id: &str) -> Result<()> {
+        self.data.lock().unwrap().jobs.retain(|job| job.id != id);
+
+        Ok(())
+    }
+
+    async fn snapshots_get_all(&self, job_id: &str) -> Result<Vec<Snapshot>> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data
+            .snapshots
+            .iter()
+            .filter(|snapshot| snapshot.job_id == job_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn snapshots_get_latest(&self, job_id: &str) -> Result<Option<Snapshot>> {
+        Ok(self
+            .snapshots_get_recent(job_id, 1)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn snapshots_get_recent(&self, job_id: &str, limit: i64) -> Result<Vec<Snapshot>> {
+        let mut snapshots = self.snapshots_get_all(job_id).await?;
+        sort_snapshots(&mut snapshots, SortOrder::NewestFirst);
+        snapshots.truncate(limit.max(0) as usize);
+
+        Ok(snapshots)
+    }
+
+    async fn snapshots_get_values(&self, job_id: &str) -> Result<Vec<Snapshot>> {
+        let mut snapshots = self.snapshots_get_all(job_id).await?;
+        snapshots.retain(|snapshot| snapshot.value.is_some());
+        sort_snapshots(&mut snapshots, SortOrder::OldestFirst);
+
+        Ok(snapshots)
+    }
+
+    async fn snapshots_add(&self, snapshot: InsertableSnapshot) -> Result<Snapshot> {
+        let snapshot = Snapshot {
+            id: ObjectId::new().to_hex(),
+            job_id: snapshot.job_id,
+            data: snapshot.data,
+            value: snapshot.value,
+            raw_data: snapshot.raw_data,
+            created_at: snapshot.created_at,
+            fetch: snapshot.fetch,
+        };
+
+        self.data.lock().unwrap().snapshots.push(snapshot.clone());
+
+        Ok(snapshot)
+    }
+
+    async fn snapshots_get_one(&self, id: &str) -> Result<Option<Snapshot>> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data
+            .snapshots
+            .iter()
+            .find(|snapshot| snapshot.id == id)
+            .cloned())
+    }
+
+    async fn snapshots_delete(&self, id: &str) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        data.snapshots.retain(|snapshot| snapshot.id != id);
+
+        Ok(())
+    }
+
+    async fn snapshots_delete_many(&self, ids: &[String]) -> Result<PruneReport> {
+        let mut data = self.data.lock().unwrap();
+
+        // Nothing is compressed or shared here, so the data of each snapshot is freed
+        let mut report = PruneReport::default();
+        data.snapshots.retain(|snapshot| {
+            let keep = !ids.contains(&snapshot.id);
+            if !keep {
+                report.snapshots_removed += 1;
+                report.bytes_removed += (snapshot.data.len()
+                    + snapshot.raw_data.as_ref().map_or(0, String::len))
+                    as u64;
+            }
+
+            keep
+        });
+
+        Ok(report)
+    }
+
+    async fn snapshots_query(&self, job_id: &str, query: &SnapshotQuery) -> Result<SnapshotPage> {
+        self.query_snapshots(query, |snapshot| snapshot.job_id == job_id)
+    }
+
+    async fn snapshots_search(
+        &self,
+        text: &str,
+        job_id: Option<&str>,
+        query: &SnapshotQuery,
+    ) -> Result<SnapshotPage> {
+        let text = text.to_lowercase();
+
+        self.query_snapshots(query, |snapshot| {
+            job_id.is_none_or(|job_id| snapshot.job_id == job_id)
+                && snapshot.data.to_lowercase().contains(text.as_str())
+        })
+    }
+
+    async fn snapshots_count(
+        &self,
+        job_id: &str,
+        after: Option<DateTime>,
+        before: Option<DateTime>,
+    ) -> Result<u64> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data
+            .snapshots
+            .iter()
+            .filter(|snapshot| snapshot.job_id == job_id && in_range(snapshot, after, before))
+            .count() as u64)
+    }
+
+    async fn snapshots_get_headers(&self, job_id: &str) -> Result<Vec<SnapshotHeader>> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data
+            .snapshots
+            .iter()
+            .filter(|snapshot| snapshot.job_id == job_id)
+            .map(|snapshot| SnapshotHeader {
+                id: snapshot.id.clone(),
+                created_at: snapshot.created_at,
+            })
+            .collect())
+    }
+
+    async fn snapshots_get_versions_back(
+        &self,
+        job_id: &str,
+        versions: u64,
+    ) -> Result<Option<Snapshot>> {
+        let mut snapshots = self.snapshots_get_all(job_id).await?;
+        sort_snapshots(&mut snapshots, SortOrder::NewestFirst);
+
+        Ok(snapshots.into_iter().nth(versions as usize))
+    }
+
+    async fn feed_items_get_all(&self, job_id: &str) -> Result<Vec<FeedItem>> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data
+            .feed_items
+            .iter()
+            .filter(|item| item.job_id == job_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn feed_items_add(&self, items: Vec<FeedItem>) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+
+        // Skip items that were already added, like the other repositories do
+        for item in items {
+            let exists = data
+                .feed_items
+                .iter()
+                .any(|existing| existing.job_id == item.job_id && existing.item_id == item.item_id);
+
+            if !exists {
+                data.feed_items.push(item);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_runs_get_all(&self, job_id: &str) -> Result<Vec<CheckRun>> {
+        let data = self.data.lock().unwrap();
+
+        let mut check_runs: Vec<CheckRun> = data
+            .check_runs
+            .iter()
+            .filter(|check_run| check_run.job_id == job_id)
+            .cloned()
+            .collect();
+        check_runs.sort_by(|a, b| {
+            b.started_at
+                .timestamp_millis()
+                .cmp(&a.started_at.timestamp_millis())
+                .then_with(|| b.id.cmp(&a.id))
+        });
+
+        Ok(check_runs)
+    }
+
+    async fn check_runs_get_latest(&self, job_id: &str) -> Result<Option<CheckRun>> {
+        Ok(self.check_runs_get_all(job_id).await?.into_iter().next())
+    }
+
+    async fn check_runs_add(&self, check_run: InsertableCheckRun) -> Result<CheckRun> {
+        let check_run = CheckRun {
+            id: ObjectId::new().to_hex(),
+            job_id: check_run.job_id,
+            started_at: check_run.started_at,
+            finished_at: check_run.finished_at,
+            outcome: check_run.outcome,
+            error: check_run.error,
+            snapshot_id: check_run.snapshot_id,
+        };
+
+        self.data.lock().unwrap().check_runs.push(check_run.clone());
+
+        Ok(check_run)
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::DateTime;
+
+use crate::{
+    error::Result,
+    model::{
+        CheckRun, FeedItem, InsertableCheckRun, InsertableJob, InsertableSnapshot, Job,
+        PruneReport, RetentionPolicy, Snapshot, SnapshotHeader, SnapshotPage, SnapshotQuery,
+    },
+    retention::snapshots_to_prune,
+};
+
+mod bodies;
+mod history;
+mod memory;
+mod mongo;
+mod postgres;
+mod sqlite;
+
+pub use self::memory::*;
+pub use self::mongo::*;
+pub use self::postgres::*;
+pub use self::sqlite::*;
+
+/// Everything the webmonitor stores: jobs, their snapshots, seen feed items and check runs.
+/// Implemented for each supported database.
+#[async_trait]
+pub trait Repository: Send + Sync {
+    async fn jobs_get_all(&self) -> Result<Vec<Job>>;
+    async fn jobs_get_one(&self, id: &str) -> Result<Option<Job>>;
+    async fn jobs_add(&self, job: InsertableJob) -> Result<Job>;
+    async fn jobs_update(&self, job: Job) -> Result<Job>;
+    async fn jobs_delete(&self, id: &str) -> Result<()>;
+
+    async fn snapshots_get_all(&self, job_id: &str) -> Result<Vec<Snapshot>>;
+    /// Returns the job's most recent snapshot. Like all snapshot queries, this orders snapshots
+    /// by creation time, and by id for snapshots created at the same time
+    async fn snapshots_get_latest(&self, job_id: &str) -> Result<Option<Snapshot>>;
+    /// Returns the given number of most recent snapshots of the job, from newest to oldest
+    async fn snapshots_get_recent(&self, job_id: &str, limit: i64) -> Result<Vec<Snapshot>>;
+    /// Returns all snapshots of the job that have a value, from oldest to newest
+    async fn snapshots_get_values(&self, job_id: &str) -> Result<Vec<Snapshot>>;
+    async fn snapshots_add(&self, snapshot: InsertableSnapshot) -> Result<Snapshot>;
+    async fn snapshots_get_one(&self, id: &str) -> Result<Option<Snapshot>>;
+    async fn snapshots_delete(&self, id: &str) -> Result<()>;
+    /// Deletes all snapshots with the given ids, reporting how many were deleted
+    /// and how many bytes the bodies that were removed along with them took
+    async fn snapshots_delete_many(&self, ids: &[String]) -> Result<PruneReport>;
+
+    /// Returns a page of the job's snapshots matching the query,
+    /// ordered by creation time (and by id for snapshots created at the same time)
+    async fn snapshots_query(&self, job_id: &str, query: &SnapshotQuery) -> Result<SnapshotPage>;
+    /// Returns a page of the snapshots of all jobs (or only the given one) matching the query
+    /// whose data may contain the text, ordered like `snapshots_query`. Databases narrow them
+    /// down as far as their index allows, which may leave snapshots that don't contain
+    /// the exact text, e.g. with another case, so callers have to check the data of each snapshot.
+    async fn snapshots_search(
+        &self,
+        text: &str,
+        job_id: Option<&str>,
+        query: &SnapshotQuery,
+    ) -> Result<SnapshotPage>;
+    /// Counts the job's snapshots created at or after `after` and before `before`
+    async fn snapshots_count(
+        &self,
+        job_id: &str,
+        after: Option<DateTime>,
+        before: Option<DateTime>,
+    ) -> Result<u64>;
+    /// Returns the ids and creation times of all snapshots of the job,
+    /// without loading their data
+    async fn snapshots_get_headers(&self, job_id: &str) -> Result<Vec<SnapshotHeader>>;
+    /// Returns the snapshot the given number of versions before the latest one,
+    /// so 0 returns the latest snapshot
+    async fn snapshots_get_versions_back(
+        &self,
+        job_id: &str,
+        versions: u64,
+    ) -> Result<Option<Snapshot>>;
+
+    /// Removes the snapshots of the job that the given policy doesn't keep,
+    /// and reports how many were removed.
+    async fn snapshots_prune(&self, job_id: &str, policy: &RetentionPolicy) -> Result<PruneReport> {
+        let headers = self.snapshots_get_headers(job_id).await?;
+
+        let ids = snapshots_to_prune(&headers, policy, DateTime::from(Utc::now()))
+            .iter()
+            .map(|header| header.id.clone())
+            .collect::<Vec<String>>();
+
+        self.snapshots_delete_many(&ids).await
+    }
+
+    async fn feed_items_get_all(&self, job_id: &str) -> Result<Vec<FeedItem>>;
+    async fn feed_items_add(&self, items: Vec<FeedItem>) -> Result<()>;
+
+    /// Returns all check runs of the job, from newest to oldest by start time
+    async fn check_runs_get_all(&self, job_id: &str) -> Result<Vec<CheckRun>>;
+    async fn check_runs_get_latest(&self, job_id: &str) -> Result<Option<CheckRun>>;
+    async fn check_runs_add(&self, check_run: InsertableCheckRun) -> Result<CheckRun>;
+}
+
+/// Connects to the database given by the uri.
+/// Uris starting with `sqlite:` open (or create) an SQLite database file at the given path,
+/// e.g. `sqlite://webmonitor.db`, `postgres://` and `postgresql://` uris connect to PostgreSQL,
+/// and `memory:` keeps everything in memory for a dry run where nothing persists.
+/// All other uris are treated as MongoDB connection strings.
+pub async fn init(uri: &str) -> Result<Arc<dyn Repository>> {
+    if uri == "memory:" {
+        Ok(Arc::new(MemoryRepository::new()))
+    } else if let Some(path) = uri.strip_prefix("sqlite:") {
+        let path = path.strip_prefix("//").unwrap_or(path);
+        Ok(Arc::new(SqliteRepository::open(path).await?))
+    } else if uri.starts_with("postgres://") || uri.starts_with("postgresql://") {
+        Ok(Arc::new(PostgresRepository::init(uri).await?))
+    } else {
+        Ok(Arc::new(MongoRepository::init(uri).await?))
+    }
+}
@@ -0,0 +1,799 @@
+use std::{collections::HashMap, env};
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use futures::StreamExt;
+
+use log::info;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, spec::BinarySubtype, Binary, Bson, DateTime, Document},
+    error::ErrorKind,
+    options::{
+        ClientOptions, FindOneAndDeleteOptions, FindOneOptions, FindOptions, ResolverConfig,
+        UpdateOptions,
+    },
+    Client, Collection, Cursor, Database,
+};
+
+use crate::{
+    error::{Result, WebmonitorError},
+    model::{
+        CheckRun, FeedItem, InsertableCheckRun, InsertableJob, InsertableSnapshot, Job,
+        PruneReport, Snapshot, SnapshotHeader, SnapshotPage, SnapshotQuery, SortOrder,
+    },
+};
+
+use super::{
+    bodies::{decompress_body, SnapshotBody},
+    history::{fetch_limit, page_from_snapshots, SnapshotCursor},
+    Repository,
+};
+
+/// Fields of a snapshot document holding body hashes, with the fields their data is loaded into
+const BODY_HASH_FIELDS: &[(&str, &str)] = &[("body_hash", "data"), ("raw_body_hash", "raw_data")];
+
+/// Stores everything in a MongoDB database, with snapshot data compressed
+/// and deduplicated in the snapshot_bodies collection.
+///
+/// Each body counts the snapshots referencing it, and is removed once that count drops to zero.
+/// Bodies are only changed with single atomic updates, so a snapshot that's added while
+/// another one is removed either keeps their shared body alive, or stores it again.
+///
+/// Bodies holding the data of snapshots of jobs with the store_search_text option also store it
+/// uncompressed in their text field, which searches match on the server. The snapshots
+/// of other jobs are all loaded for the caller to check, which is much slower.
+pub struct MongoRepository {
+    database: Database,
+    job_collection: Collection,
+    snapshot_collection: Collection,
+    snapshot_body_collection: Collection,
+    feed_item_collection: Collection,
+    check_run_collection: Collection,
+}
+
+impl MongoRepository {
+    pub async fn init(client_uri: &str) -> Result<Self> {
+        let database_name = env::var("DATABASE_NAME")
+            .map_err(|_| WebmonitorError::MissingEnvironmentVariable("DATABASE_NAME".into()))?;
+
+        let options =
+            ClientOptions::parse_with_resolver_config(client_uri, ResolverConfig::cloudflare())
+                .await?;
+
+        let client = Client::with_options(options)?;
+        let database = client.database(database_name.as_str());
+        let job_collection = database.collection("jobs");
+        let snapshot_collection = database.collection("snapshots");
+        let snapshot_body_collection = database.collection("snapshot_bodies");
+        let feed_item_collection = database.collection("feed_items");
+        let check_run_collection = database.collection("check_runs");
+        info!("Connected to database.");
+
+        let repository = Self {
+            database,
+            job_collection,
+            snapshot_collection,
+            snapshot_body_collection,
+            feed_item_collection,
+            check_run_collection,
+        };
+        repository.migrate_snapshot_timestamps().await?;
+        repository.migrate_body_references().await?;
+        repository.migrate_snapshot_bodies().await?;
+        repository.drop_search_index().await?;
+
+        Ok(repository)
+    }
+
+    /// Sets the creation time of snapshots that were stored before snapshots had one,
+    /// using the timestamp contained in their ObjectId
+    async fn migrate_snapshot_timestamps(&self) -> Result<()> {
+        let filter = doc! { "created_at": { "$exists": false } };
+        let update = vec![doc! { "$set": { "created_at": { "$toDate": "$_id" } } }];
+
+        self.snapshot_collection
+            .update_many(filter, update, None)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Counts the snapshots referencing each body that was stored before bodies counted them.
+    /// If a snapshot using the body is added meanwhile, it may be counted twice,
+    /// which only keeps the body around longer, but never removes it while it's still used.
+    async fn migrate_body_references(&self) -> Result<()> {
+        let filter = doc! { "references": { "$exists": false } };
+        let options = FindOptions::builder().projection(doc! { "_id": 1 }).build();
+        let mut cursor = self.snapshot_body_collection.find(filter, options).await?;
+
+        while let Some(doc) = cursor.next().await {
+            let hash = match doc?.get_str("_id") {
+                Ok(hash) => hash.to_string(),
+                Err(_) => continue,
+            };
+
+            let filter = doc! { "$or": [{ "body_hash": &hash }, { "raw_body_hash": &hash }] };
+            let references = self
+                .snapshot_collection
+                .count_documents(filter, None)
+                .await?;
+
+            let filter = doc! { "_id": &hash };
+            let update = doc! { "$inc": { "references": references as i64 } };
+            self.snapshot_body_collection
+                .update_one(filter, update, None)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the data of snapshots that were stored before their bodies were compressed
+    /// and deduplicated into the snapshot_bodies collection
+    async fn migrate_snapshot_bodies(&self) -> Result<()> {
+        let filter = doc! { "body_hash": { "$exists": false } };
+        let mut cursor = self.snapshot_collection.find(filter, None).await?;
+
+        while let Some(doc) = cursor.next().await {
+            let snapshot: Snapshot = bson::from_document(doc?)?;
+            let store_text = self.stores_search_text(&snapshot.job_id).await?;
+            let body_hash = self.store_body(&snapshot.data, store_text).await?;
+
+            let filter = doc! { "_id": ObjectId::with_string(&snapshot.id)? };
+            let update = doc! { "$set": { "body_hash": body_hash }, "$unset": { "data": "" } };
+            self.snapshot_collection
+                .update_one(filter, update, None)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Drops the text index that snapshots were searched with before,
+    /// since searches match the stored text of bodies with a regular expression now
+    async fn drop_search_index(&self) -> Result<()> {
+        let command = doc! { "dropIndexes": "snapshot_bodies", "index": "text_search" };
+
+        match self.database.run_command(command, None).await {
+            Err(e) if !is_missing_index_error(&e) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether the job stores the text of its snapshots to search them
+    async fn stores_search_text(&self, job_id: &str) -> Result<bool> {
+        let filter = doc! { "_id": ObjectId::with_string(job_id)? };
+        let options = FindOneOptions::builder()
+            .projection(doc! { "store_search_text": 1 })
+            .build();
+
+        let job = self.job_collection.find_one(filter, options).await?;
+
+        Ok(job.is_some_and(|job| job.get_bool("store_search_text").unwrap_or(false)))
+    }
+
+    /// Stores the compressed data unless an identical body is already stored,
+    /// counts the new reference to it and returns the hash it's addressed by.
+    /// If asked to, the data is also stored uncompressed as text, which searches can match.
+    async fn store_body(&self, data: &str, store_text: bool) -> Result<String> {
+        let body = SnapshotBody::compress(data)?;
+
+        let filter = doc! { "_id": &body.hash };
+        let compressed = Binary {
+            subtype: BinarySubtype::Generic,
+            bytes: body.compressed,
+        };
+        let update = if store_text {
+            doc! {
+                "$setOnInsert": { "data": compressed },
+                "$set": { "text": data },
+                "$inc": { "references": 1 }
+            }
+        } else {
+            doc! {
+                "$setOnInsert": { "data": compressed, "text": Bson::Null },
+                "$inc": { "references": 1 }
+            }
+        };
+        let options = UpdateOptions::builder().upsert(true).build();
+
+        self.snapshot_body_collection
+            .update_one(filter, update, options)
+            .await?;
+
+        Ok(body.hash)
+    }
+
+    /// Removes a reference from the bodies with the given hashes, once per occurrence,
+    /// and removes the bodies that aren't referenced by any snapshot anymore,
+    /// returning the compressed size of the removed bodies
+    async fn release_bodies(&self, hashes: Vec<String>) -> Result<u64> {
+        let mut references: HashMap<String, i64> = HashMap::new();
+        for hash in hashes {
+            *references.entry(hash).or_insert(0) += 1;
+        }
+
+        for (hash, count) in &references {
+            let filter = doc! { "_id": hash };
+            let update = doc! { "$inc": { "references": -count } };
+            self.snapshot_body_collection
+                .update_one(filter, update, None)
+                .await?;
+        }
+
+        // Each body is removed on its own, so its size is only counted if it was removed here
+        let options = FindOneAndDeleteOptions::builder()
+            .projection(doc! { "data": 1 })
+            .build();
+        let mut bytes_removed = 0;
+        for hash in references.into_keys() {
+            let filter = doc! { "_id": hash, "references": { "$lte": 0 } };
+            let removed = self
+                .snapshot_body_collection
+                .find_one_and_delete(filter, options.clone())
+                .await?;
+
+            if let Some(removed) = removed {
+                if let Ok(compressed) = removed.get_binary_generic("data") {
+                    bytes_removed += compressed.len() as u64;
+                }
+            }
+        }
+
+        Ok(bytes_removed)
+    }
+
+    /// Loads and decompresses the bodies with the given hashes in a single query
+    async fn load_bodies(&self, hashes: Vec<String>) -> Result<HashMap<String, String>> {
+        let filter = doc! { "_id": { "$in": hashes } };
+        let options = FindOptions::builder()
+            .projection(doc! { "data": 1 })
+            .build();
+        let mut cursor = self.snapshot_body_collection.find(filter, options).await?;
+
+        let mut bodies = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            if let (Ok(hash), Ok(compressed)) = (doc.get_str("_id"), doc.get_binary_generic("data"))
+            {
+                bodies.insert(hash.to_string(), decompress_body(compressed)?);
+            }
+        }
+
+        Ok(bodies)
+    }
+
+    /// Parses snapshot documents, loading their data (and raw data, if stored)
+    /// from the snapshot_bodies collection with a single query for all of them
+    async fn snapshots_from_documents(&self, documents: Vec<Document>) -> Result<Vec<Snapshot>> {
+        let mut hashes = documents
+            .iter()
+            .flat_map(|document| {
+                BODY_HASH_FIELDS
+                    .iter()
+                    .filter_map(move |(field, _)| document.get_str(field).ok())
+            })
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        hashes.sort();
+        hashes.dedup();
+
+        let bodies = self.load_bodies(hashes).await?;
+
+        documents
+            .into_iter()
+            .map(|mut document| {
+                for (hash_field, data_field) in BODY_HASH_FIELDS {
+                    if let Ok(hash) = document.get_str(hash_field).map(str::to_string) {
+                        let data = bodies
+                            .get(&hash)
+                            .cloned()
+                            .ok_or(WebmonitorError::SnapshotBodyMissing(hash))?;
+                        document.insert(*data_field, data);
+                    }
+                }
+
+                Ok(bson::from_document(document)?)
+            })
+            .collect()
+    }
+
+    /// Selects a page of the snapshots matching the filter,
+    /// in the query's order and starting after its cursor
+    async fn query_page(
+        &self,
+        mut filter: Document,
+        query: &SnapshotQuery,
+    ) -> Result<SnapshotPage> {
+        let sort = paginate(&mut filter, query)?;
+        let options = FindOptions::builder()
+            .sort(sort)
+            .limit(fetch_limit(query.limit))
+            .build();
+
+        let cursor = self.snapshot_collection.find(filter, options).await?;
+
+        self.page_from_cursor(cursor, query).await
+    }
+
+    /// Loads the snapshots of a page from the cursor of its query
+    async fn page_from_cursor(
+        &self,
+        mut cursor: Cursor<Document>,
+        query: &SnapshotQuery,
+    ) -> Result<SnapshotPage> {
+        let mut documents: Vec<Document> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            documents.push(doc?);
+        }
+        let snapshots = self.snapshots_from_documents(documents).await?;
+
+        Ok(page_from_snapshots(snapshots, query.limit))
+    }
+
+    /// Parses a snapshot document, loading its data (and raw data, if stored)
+    /// from the snapshot_bodies collection
+    async fn snapshot_from_document(&self, document: Document) -> Result<Snapshot> {
+        let mut snapshots = self.snapshots_from_documents(vec![document]).await?;
+
+        Ok(snapshots.remove(0))
+    }
+}
+
+#[async_trait]
+impl Repository for MongoRepository {
+    async fn jobs_get_all(&self) -> Result<Vec<Job>> {
+        let mut cursor = self.job_collection.find(None, None).await?;
+        let mut jobs: Vec<Job> = Vec::new();
+
+        while let Some(doc) = cursor.next().await {
+            jobs.push(bson::from_document(doc?)?);
+        }
+
+        Ok(jobs)
+    }
+
+    async fn jobs_get_one(&self, id: &str) -> Result<Option<Job>> {
+        let filter = doc! { "_id": ObjectId::with_string(id)? };
+        let option = self.job_collection.find_one(filter, None).await?;
+
+        match option {
+            Some(doc) => Ok(Some(bson::from_document(doc)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn jobs_add(&self, job: InsertableJob) -> Result<Job> {
+        let doc = bson::to_document(&job)?;
+
+        let result = self.job_collection.insert_one(doc, None).await?;
+        let id = result.inserted_id.as_object_id().unwrap().to_hex();
+
+        Ok(Job {
+            id,
+            name: job.name,
+            url: job.url,
+            show_diff: job.show_diff,
+            mode: job.mode,
+            change_detector: job.change_detector,
+            triggers: job.triggers,
+            confirmation: job.confirmation,
+            ignore_recent_snapshots: job.ignore_recent_snapshots,
+            retention: job.retention,
+            store_raw: job.store_raw,
+            store_search_text: job.store_search_text,
+            interval: job.interval,
+            filters: job.filters,
+            notifications: job.notifications,
+        })
+    }
+
+    async fn jobs_update(&self, job: Job) -> Result<Job> {
+        let filter = doc! { "_id": &job.id.as_str() };
+        let doc = bson::to_document(&job)?;
+
+        self.job_collection.update_one(filter, doc, None).await?;
+
+        Ok(job)
+    }
+
+    async fn jobs_delete(&self, id: &str) -> Result<()> {
+        let filter = doc! { "_id": ObjectId::with_string(id)? };
+
+        self.job_collection.delete_one(filter, None).await?;
+
+        Ok(())
+    }
+
+    async fn snapshots_get_all(&self, job_id: &str) -> Result<Vec<Snapshot>> {
+        let filter = doc! { "job_id": job_id };
+
+        let mut cursor = self.snapshot_collection.find(filter, None).await?;
+
+        let mut documents: Vec<Document> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            documents.push(doc?);
+        }
+
+        self.snapshots_from_documents(documents).await
+    }
+
+    async fn snapshots_get_latest(&self, job_id: &str) -> Result<Option<Snapshot>> {
+        let filter = doc! { "job_id": job_id };
+        let options = FindOneOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .build();
+
+        let option = self.snapshot_collection.find_one(filter, options).await?;
+        match option {
+            Some(doc) => Ok(Some(self.snapshot_from_document(doc).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn snapshots_get_recent(&self, job_id: &str, limit: i64) -> Result<Vec<Snapshot>> {
+        let filter = doc! { "job_id": job_id };
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit)
+            .build();
+
+        let mut cursor = self.snapshot_collection.find(filter, options).await?;
+
+        let mut documents: Vec<Document> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            documents.push(doc?);
+        }
+
+        self.snapshots_from_documents(documents).await
+    }
+
+    async fn snapshots_get_values(&self, job_id: &str) -> Result<Vec<Snapshot>> {
+        let filter = doc! { "job_id": job_id, "value": { "$ne": null } };
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": 1, "_id": 1 })
+            .build();
+
+        let mut cursor = self.snapshot_collection.find(filter, options).await?;
+
+        let mut documents: Vec<Document> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            documents.push(doc?);
+        }
+
+        self.snapshots_from_documents(documents).await
+    }
+
+    async fn snapshots_add(&self, snapshot: InsertableSnapshot) -> Result<Snapshot> {
+        let store_text = self.stores_search_text(&snapshot.job_id).await?;
+        let body_hash = self.store_body(&snapshot.data, store_text).await?;
+
+        let mut doc = bson::to_document(&snapshot)?;
+        doc.remove("data");
+        doc.remove("raw_data");
+        doc.insert("body_hash", body_hash);
+        if let Some(raw_data) = &snapshot.raw_data {
+            doc.insert("raw_body_hash", self.store_body(raw_data, false).await?);
+        }
+
+        let result = self.snapshot_collection.insert_one(doc, None).await?;
+        let id = result.inserted_id.as_object_id().unwrap().to_hex();
+
+        Ok(Snapshot {
+            id,
+            job_id: snapshot.job_id,
+            data: snapshot.data,
+            value: snapshot.value,
+            raw_data: snapshot.raw_data,
+            created_at: snapshot.created_at,
+            fetch: snapshot.fetch,
+        })
+    }
+
+    async fn snapshots_get_one(&self, id: &str) -> Result<Option<Snapshot>> {
+        let filter = doc! { "_id": ObjectId::with_string(id)? };
+
+        let option = self.snapshot_collection.find_one(filter, None).await?;
+        match option {
+            Some(doc) => Ok(Some(self.snapshot_from_document(doc).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn snapshots_delete(&self, id: &str) -> Result<()> {
+        self.snapshots_delete_many(&[id.to_string()]).await?;
+
+        Ok(())
+    }
+
+    async fn snapshots_delete_many(&self, ids: &[String]) -> Result<PruneReport> {
+        let object_ids = ids
+            .iter()
+            .map(|id| ObjectId::with_string(id))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let options = FindOneAndDeleteOptions::builder()
+            .projection(doc! { "body_hash": 1, "raw_body_hash": 1 })
+            .build();
+
+        // Only the bodies of snapshots that were actually deleted here are released,
+        // so deleting the same snapshot concurrently doesn't release its bodies twice
+        let mut deleted = 0;
+        let mut hashes = Vec::new();
+        for object_id in object_ids {
+            let filter = doc! { "_id": object_id };
+            let document = self
+                .snapshot_collection
+                .find_one_and_delete(filter, options.clone())
+                .await?;
+
+            if let Some(document) = document {
+                deleted += 1;
+                hashes.extend(
+                    BODY_HASH_FIELDS
+                        .iter()
+                        .filter_map(|(field, _)| document.get_str(field).ok())
+                        .map(str::to_string),
+                );
+            }
+        }
+        let bytes_removed = self.release_bodies(hashes).await?;
+
+        Ok(PruneReport {
+            snapshots_removed: deleted,
+            bytes_removed,
+        })
+    }
+
+    async fn snapshots_query(&self, job_id: &str, query: &SnapshotQuery) -> Result<SnapshotPage> {
+        let filter = range_filter(Some(job_id), query.after, query.before);
+
+        self.query_page(filter, query).await
+    }
+
+    async fn snapshots_search(
+        &self,
+        text: &str,
+        job_id: Option<&str>,
+        query: &SnapshotQuery,
+    ) -> Result<SnapshotPage> {
+        let mut filter = range_filter(job_id, query.after, query.before);
+        let sort = paginate(&mut filter, query)?;
+
+        // Only the bodies of the snapshots in the queried range are matched, one page at a time.
+        // Bodies without stored text can't be matched here, so their snapshots are all returned.
+        let text_matches = doc! {
+            "$cond": [
+                { "$eq": [{ "$type": "$text" }, "string"] },
+                {
+                    "$regexMatch": { "input": "$text", "regex": regex::escape(text), "options": "i" }
+                },
+                true
+            ]
+        };
+        let mut pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$sort": sort },
+            doc! {
+                "$lookup": {
+                    "from": "snapshot_bodies",
+                    "let": { "hash": "$body_hash" },
+                    "pipeline": [
+                        {
+                            "$match": {
+                                "$expr": { "$and": [{ "$eq": ["$_id", "$$hash"] }, text_matches] }
+                            }
+                        },
+                        { "$project": { "_id": 1 } }
+                    ],
+                    "as": "matching_bodies"
+                }
+            },
+            doc! { "$match": { "matching_bodies": { "$ne": [] } } },
+        ];
+        if let Some(limit) = fetch_limit(query.limit) {
+            pipeline.push(doc! { "$limit": limit });
+        }
+        pipeline.push(doc! { "$project": { "matching_bodies": 0 } });
+
+        let cursor = self.snapshot_collection.aggregate(pipeline, None).await?;
+
+        self.page_from_cursor(cursor, query).await
+    }
+
+    async fn snapshots_count(
+        &self,
+        job_id: &str,
+        after: Option<DateTime>,
+        before: Option<DateTime>,
+    ) -> Result<u64> {
+        let filter = range_filter(Some(job_id), after, before);
+
+        Ok(self
+            .snapshot_collection
+            .count_documents(filter, None)
+            .await? as u64)
+    }
+
+    async fn snapshots_get_headers(&self, job_id: &str) -> Result<Vec<SnapshotHeader>> {
+        let filter = doc! { "job_id": job_id };
+        let options = FindOptions::builder()
+            .projection(doc! { "_id": 1, "created_at": 1 })
+            .build();
+
+        let mut cursor = self.snapshot_collection.find(filter, options).await?;
+
+        let mut headers: Vec<SnapshotHeader> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            headers.push(bson::from_document(doc?)?);
+        }
+
+        Ok(headers)
+    }
+
+    async fn snapshots_get_versions_back(
+        &self,
+        job_id: &str,
+        versions: u64,
+    ) -> Result<Option<Snapshot>> {
+        let filter = doc! { "job_id": job_id };
+        let options = FindOneOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(versions as i64)
+            .build();
+
+        let option = self.snapshot_collection.find_one(filter, options).await?;
+        match option {
+            Some(doc) => Ok(Some(self.snapshot_from_document(doc).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn feed_items_get_all(&self, job_id: &str) -> Result<Vec<FeedItem>> {
+        let filter = doc! { "job_id": job_id };
+
+        let mut cursor = self.feed_item_collection.find(filter, None).await?;
+
+        let mut items: Vec<FeedItem> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            items.push(bson::from_document(doc?)?);
+        }
+
+        Ok(items)
+    }
+
+    async fn feed_items_add(&self, items: Vec<FeedItem>) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        // Upserts skip items that were already added, like the unique keys of the SQL databases
+        let options = UpdateOptions::builder().upsert(true).build();
+        for item in items {
+            let filter = doc! { "job_id": &item.job_id, "item_id": &item.item_id };
+            let update = doc! { "$setOnInsert": bson::to_document(&item)? };
+            self.feed_item_collection
+                .update_one(filter, update, options.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn check_runs_get_all(&self, job_id: &str) -> Result<Vec<CheckRun>> {
+        let filter = doc! { "job_id": job_id };
+        let options = FindOptions::builder()
+            .sort(doc! { "started_at": -1, "_id": -1 })
+            .build();
+
+        let mut cursor = self.check_run_collection.find(filter, options).await?;
+
+        let mut check_runs: Vec<CheckRun> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            check_runs.push(bson::from_document(doc?)?);
+        }
+
+        Ok(check_runs)
+    }
+
+    async fn check_runs_get_latest(&self, job_id: &str) -> Result<Option<CheckRun>> {
+        let filter = doc! { "job_id": job_id };
+        let options = FindOneOptions::builder()
+            .sort(doc! { "started_at": -1, "_id": -1 })
+            .build();
+
+        let option = self.check_run_collection.find_one(filter, options).await?;
+        match option {
+            Some(doc) => Ok(Some(bson::from_document(doc)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn check_runs_add(&self, check_run: InsertableCheckRun) -> Result<CheckRun> {
+        let doc = bson::to_document(&check_run)?;
+
+        let result = self.check_run_collection.insert_one(doc, None).await?;
+        let id = result.inserted_id.as_object_id().unwrap().to_hex();
+
+        Ok(CheckRun {
+            id,
+            job_id: check_run.job_id,
+            started_at: check_run.started_at,
+            finished_at: check_run.finished_at,
+            outcome: check_run.outcome,
+            error: check_run.error,
+            snapshot_id: check_run.snapshot_id,
+        })
+    }
+}
+
+/// Adds the condition for the snapshots following the query's cursor to the filter,
+/// and returns the order the query's snapshots are sorted in
+fn paginate(filter: &mut Document, query: &SnapshotQuery) -> Result<Document> {
+    if let Some(cursor) = &query.cursor {
+        let cursor = SnapshotCursor::parse(cursor)?;
+        let operator = match query.order {
+            SortOrder::NewestFirst => "$lt",
+            SortOrder::OldestFirst => "$gt",
+        };
+        let created_at = Utc.timestamp_millis(cursor.created_at);
+        let id = ObjectId::with_string(&cursor.id)
+            .map_err(|_| WebmonitorError::InvalidCursor(cursor.encode()))?;
+
+        filter.insert(
+            "$or",
+            vec![
+                doc! { "created_at": { operator: created_at } },
+                doc! {
+                    "created_at": created_at,
+                    "_id": { operator: id }
+                },
+            ],
+        );
+    }
+
+    let direction = match query.order {
+        SortOrder::NewestFirst => -1,
+        SortOrder::OldestFirst => 1,
+    };
+
+    Ok(doc! { "created_at": direction, "_id": direction })
+}
+
+/// Returns whether the error tells that an index to drop didn't exist,
+/// or that its collection wasn't even created yet
+fn is_missing_index_error(error: &mongodb::error::Error) -> bool {
+    const NAMESPACE_NOT_FOUND: i32 = 26;
+    const INDEX_NOT_FOUND: i32 = 27;
+
+    matches!(
+        &error.kind,
+        ErrorKind::CommandError(error)
+            if error.code == NAMESPACE_NOT_FOUND || error.code == INDEX_NOT_FOUND
+    )
+}
+
+/// Returns the filter to select the snapshots (of all jobs or only the given one)
+/// created at or after `after` and before `before`
+fn range_filter(
+    job_id: Option<&str>,
+    after: Option<DateTime>,
+    before: Option<DateTime>,
+) -> Document {
+    let mut filter = Document::new();
+    if let Some(job_id) = job_id {
+        filter.insert("job_id", job_id);
+    }
+
+    let mut created_at = Document::new();
+    if let Some(after) = after {
+        created_at.insert("$gte", after.0);
+    }
+    if let Some(before) = before {
+        created_at.insert("$lt", before.0);
+    }
+    if !created_at.is_empty() {
+        filter.insert("created_at", created_at);
+    }
+
+    filter
+}
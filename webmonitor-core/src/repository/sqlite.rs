@@ -0,0 +1,977 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use log::info;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use rusqlite::{params, Connection, Row, ToSql};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::Result,
+    model::{
+        CheckRun, FeedItem, InsertableCheckRun, InsertableJob, InsertableSnapshot, Job,
+        PruneReport, Snapshot, SnapshotHeader, SnapshotPage, SnapshotQuery, SortOrder,
+    },
+};
+
+use super::{
+    bodies::{decompress_body, row_data, row_raw_data, SnapshotBody},
+    history::{fetch_limit, page_from_snapshots, SnapshotCursor},
+    Repository,
+};
+
+/// Schema migrations, applied in order and tracked by the database's user_version.
+/// Existing entries must never be changed, only new ones appended.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE IF NOT EXISTS jobs (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        url TEXT NOT NULL,
+        show_diff INTEGER NOT NULL,
+        mode TEXT NOT NULL,
+        change_detector TEXT NOT NULL,
+        triggers TEXT NOT NULL,
+        confirmation TEXT,
+        ignore_recent_snapshots INTEGER NOT NULL,
+        interval INTEGER NOT NULL,
+        filters TEXT NOT NULL,
+        notifications TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id TEXT PRIMARY KEY,
+        job_id TEXT NOT NULL,
+        data TEXT NOT NULL,
+        value REAL,
+        created_at INTEGER NOT NULL,
+        fetch TEXT
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_job_id ON snapshots (job_id, id);
+
+    CREATE TABLE IF NOT EXISTS feed_items (
+        job_id TEXT NOT NULL,
+        item_id TEXT NOT NULL,
+        PRIMARY KEY (job_id, item_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS check_runs (
+        id TEXT PRIMARY KEY,
+        job_id TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        finished_at INTEGER NOT NULL,
+        outcome TEXT NOT NULL,
+        error TEXT,
+        snapshot_id TEXT
+    );
+    CREATE INDEX IF NOT EXISTS check_runs_job_id ON check_runs (job_id, id);
+    ",
+    "ALTER TABLE jobs ADD COLUMN retention TEXT;",
+    "
+    CREATE TABLE snapshot_bodies (
+        hash TEXT PRIMARY KEY,
+        data BLOB NOT NULL
+    );
+
+    ALTER TABLE snapshots RENAME TO legacy_snapshots;
+    CREATE TABLE snapshots (
+        id TEXT PRIMARY KEY,
+        job_id TEXT NOT NULL,
+        body_hash TEXT,
+        data TEXT,
+        value REAL,
+        created_at INTEGER NOT NULL,
+        fetch TEXT
+    );
+    INSERT INTO snapshots (id, job_id, data, value, created_at, fetch)
+        SELECT id, job_id, data, value, created_at, fetch FROM legacy_snapshots;
+    DROP TABLE legacy_snapshots;
+
+    CREATE INDEX snapshots_job_id ON snapshots (job_id, id);
+    CREATE INDEX snapshots_body_hash ON snapshots (body_hash);
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN store_raw INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE snapshots ADD COLUMN raw_body_hash TEXT;
+    CREATE INDEX snapshots_raw_body_hash ON snapshots (raw_body_hash);
+    ",
+    "CREATE INDEX snapshots_job_id_created_at ON snapshots (job_id, created_at, id);",
+    "
+    ALTER TABLE snapshot_bodies ADD COLUMN search_id INTEGER;
+    CREATE INDEX snapshot_bodies_search_id ON snapshot_bodies (search_id);
+    CREATE VIRTUAL TABLE snapshot_search USING fts5(data, content = '', tokenize = 'trigram');
+    ",
+    "ALTER TABLE jobs ADD COLUMN store_search_text INTEGER NOT NULL DEFAULT 0;",
+];
+
+const JOB_COLUMNS: &str = "id, name, url, show_diff, mode, change_detector, triggers, \
+    confirmation, ignore_recent_snapshots, retention, store_raw, store_search_text, interval, \
+    filters, notifications";
+const SNAPSHOT_COLUMNS: &str = "id, job_id, body_hash, raw_body_hash, value, created_at, fetch";
+const SNAPSHOT_SELECT: &str = "SELECT snapshots.id, job_id, value, created_at, fetch, \
+    snapshot_bodies.data, snapshots.data, raw_bodies.data, snapshots.raw_body_hash FROM snapshots \
+    LEFT JOIN snapshot_bodies ON snapshot_bodies.hash = snapshots.body_hash \
+    LEFT JOIN snapshot_bodies AS raw_bodies ON raw_bodies.hash = snapshots.raw_body_hash";
+const CHECK_RUN_COLUMNS: &str = "id, job_id, started_at, finished_at, outcome, error, snapshot_id";
+/// The trigram index only finds texts that are at least this many characters long
+const TRIGRAM_LENGTH: usize = 3;
+
+/// Stores everything in an embedded SQLite database file.
+/// Nested options like filters and notifications are stored as JSON text,
+/// and snapshot data is compressed and deduplicated in the snapshot_bodies table.
+///
+/// The data of the bodies is searchable through the snapshot_search FTS5 table,
+/// which only holds the trigram index and not the data itself.
+/// A body's search_id is the rowid of its entry in that table.
+///
+/// Ids are generated in the same format as MongoDB's ObjectIds,
+/// so they sort by creation time and are interchangeable between both databases.
+pub struct SqliteRepository {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteRepository {
+    /// Opens the database file at the given path, creating it and its tables if needed
+    pub async fn open(path: &str) -> Result<Self> {
+        let database_path = path.to_string();
+        let connection = tokio::task::spawn_blocking(move || -> Result<Connection> {
+            let mut connection = Connection::open(database_path)?;
+            migrate(&mut connection)?;
+
+            Ok(connection)
+        })
+        .await??;
+        info!("Opened SQLite database at {}.", path);
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs the given work with the connection on a blocking thread, as rusqlite's calls
+    /// (and compressing snapshot bodies) would otherwise stall the async runtime
+    async fn with_connection<T, F>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+
+        tokio::task::spawn_blocking(move || work(&mut connection.lock().unwrap())).await?
+    }
+}
+
+fn query_all<T>(
+    connection: &Connection,
+    sql: &str,
+    params: &[&dyn ToSql],
+    map: fn(&Row) -> Result<T>,
+) -> Result<Vec<T>> {
+    let mut statement = connection.prepare(sql)?;
+    let mut rows = statement.query(params)?;
+
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        items.push(map(row)?);
+    }
+
+    Ok(items)
+}
+
+fn query_one<T>(
+    connection: &Connection,
+    sql: &str,
+    params: &[&dyn ToSql],
+    map: fn(&Row) -> Result<T>,
+) -> Result<Option<T>> {
+    let mut statement = connection.prepare(sql)?;
+    let mut rows = statement.query(params)?;
+
+    rows.next()?.map(map).transpose()
+}
+
+fn execute(connection: &Connection, sql: &str, params: &[&dyn ToSql]) -> Result<()> {
+    connection.execute(sql, params)?;
+
+    Ok(())
+}
+
+/// Applies all migrations that weren't applied to the database yet,
+/// each in its own transaction
+fn migrate(connection: &mut Connection) -> Result<()> {
+    let current_version: i64 =
+        connection.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current_version as usize) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", &(index as i64 + 1))?;
+        transaction.commit()?;
+    }
+
+    migrate_snapshot_bodies(connection)?;
+    index_snapshot_bodies(connection)
+}
+
+/// Moves the data of snapshots that were stored before their bodies were compressed
+/// and deduplicated into the snapshot_bodies table
+fn migrate_snapshot_bodies(connection: &mut Connection) -> Result<()> {
+    let transaction = connection.transaction()?;
+
+    let legacy_snapshots = {
+        let mut statement =
+            transaction.prepare("SELECT id, data FROM snapshots WHERE body_hash IS NULL")?;
+        let rows = statement.query_map(params![], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        rows.collect::<rusqlite::Result<Vec<(String, String)>>>()?
+    };
+
+    for (id, data) in legacy_snapshots {
+        let body_hash = store_body(&transaction, data.as_str())?;
+        transaction.execute(
+            "UPDATE snapshots SET body_hash = ?, data = NULL WHERE id = ?",
+            params![body_hash, id],
+        )?;
+    }
+
+    transaction.commit()?;
+
+    Ok(())
+}
+
+/// Adds the data of bodies that were stored before snapshots were searchable to the search index.
+/// Bodies that only hold the raw data of snapshots aren't searched, so they're left out.
+fn index_snapshot_bodies(connection: &mut Connection) -> Result<()> {
+    let transaction = connection.transaction()?;
+
+    let bodies = query_all(
+        &transaction,
+        "SELECT hash, data FROM snapshot_bodies WHERE search_id IS NULL \
+            AND EXISTS (SELECT 1 FROM snapshots WHERE body_hash = snapshot_bodies.hash)",
+        params![],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)),
+    )?;
+
+    for (hash, compressed) in bodies {
+        let data = decompress_body(&compressed)?;
+        index_body(&transaction, hash.as_str(), data.as_str())?;
+    }
+
+    transaction.commit()?;
+
+    Ok(())
+}
+
+/// Adds the data of the stored body with the given hash to the search index,
+/// unless it's already indexed
+fn index_body(connection: &Connection, hash: &str, data: &str) -> Result<()> {
+    let search_id = query_one(
+        connection,
+        "SELECT search_id FROM snapshot_bodies WHERE hash = ?",
+        params![hash],
+        |row| Ok(row.get::<_, Option<i64>>(0)?),
+    )?;
+
+    if let Some(None) = search_id {
+        connection.execute(
+            "INSERT INTO snapshot_search (data) VALUES (?)",
+            params![data],
+        )?;
+        connection.execute(
+            "UPDATE snapshot_bodies SET search_id = ? WHERE hash = ?",
+            params![connection.last_insert_rowid(), hash],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Removes the stored body with the given hash and its search index entry,
+/// unless it's still used by a snapshot, and returns the compressed size it took
+fn remove_unused_body(connection: &Connection, hash: &str) -> Result<u64> {
+    let unused = query_one(
+        connection,
+        "SELECT search_id, data FROM snapshot_bodies WHERE hash = ? AND NOT EXISTS \
+            (SELECT 1 FROM snapshots WHERE body_hash = ? OR raw_body_hash = ?)",
+        params![hash, hash, hash],
+        |row| Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Vec<u8>>(1)?)),
+    )?;
+
+    if let Some((search_id, compressed)) = unused {
+        // The search table doesn't store the data, so it has to be given to remove its entry
+        if let Some(search_id) = search_id {
+            connection.execute(
+                "INSERT INTO snapshot_search (snapshot_search, rowid, data) \
+                    VALUES ('delete', ?, ?)",
+                params![search_id, decompress_body(&compressed)?],
+            )?;
+        }
+        connection.execute("DELETE FROM snapshot_bodies WHERE hash = ?", params![hash])?;
+
+        Ok(compressed.len() as u64)
+    } else {
+        Ok(0)
+    }
+}
+
+/// Stores the compressed data unless an identical body is already stored,
+/// and returns the hash it's addressed by
+fn store_body(connection: &Connection, data: &str) -> Result<String> {
+    let body = SnapshotBody::compress(data)?;
+
+    connection.execute(
+        "INSERT OR IGNORE INTO snapshot_bodies (hash, data) VALUES (?, ?)",
+        params![body.hash, body.compressed],
+    )?;
+
+    Ok(body.hash)
+}
+
+#[async_trait]
+impl Repository for SqliteRepository {
+    async fn jobs_get_all(&self) -> Result<Vec<Job>> {
+        self.with_connection(|connection| {
+            let sql = format!("SELECT {} FROM jobs", JOB_COLUMNS);
+
+            query_all(connection, sql.as_str(), params![], job_from_row)
+        })
+        .await
+    }
+
+    async fn jobs_get_one(&self, id: &str) -> Result<Option<Job>> {
+        let id = id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!("SELECT {} FROM jobs WHERE id = ?", JOB_COLUMNS);
+
+            query_one(connection, sql.as_str(), params![id], job_from_row)
+        })
+        .await
+    }
+
+    async fn jobs_add(&self, job: InsertableJob) -> Result<Job> {
+        let job = Job {
+            id: ObjectId::new().to_hex(),
+            name: job.name,
+            url: job.url,
+            show_diff: job.show_diff,
+            mode: job.mode,
+            change_detector: job.change_detector,
+            triggers: job.triggers,
+            confirmation: job.confirmation,
+            ignore_recent_snapshots: job.ignore_recent_snapshots,
+            retention: job.retention,
+            store_raw: job.store_raw,
+            store_search_text: job.store_search_text,
+            interval: job.interval,
+            filters: job.filters,
+            notifications: job.notifications,
+        };
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "INSERT INTO jobs ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                JOB_COLUMNS
+            );
+            execute(
+                connection,
+                sql.as_str(),
+                params![
+                    job.id,
+                    job.name,
+                    job.url,
+                    job.show_diff,
+                    to_json(&job.mode)?,
+                    to_json(&job.change_detector)?,
+                    to_json(&job.triggers)?,
+                    optional_to_json(&job.confirmation)?,
+                    job.ignore_recent_snapshots,
+                    optional_to_json(&job.retention)?,
+                    job.store_raw,
+                    job.store_search_text,
+                    job.interval as i64,
+                    to_json(&job.filters)?,
+                    to_json(&job.notifications)?,
+                ],
+            )?;
+
+            Ok(job)
+        })
+        .await
+    }
+
+    async fn jobs_update(&self, job: Job) -> Result<Job> {
+        self.with_connection(move |connection| {
+            execute(
+                connection,
+                "UPDATE jobs SET name = ?, url = ?, show_diff = ?, mode = ?, change_detector = ?, \
+                    triggers = ?, confirmation = ?, ignore_recent_snapshots = ?, retention = ?, \
+                    store_raw = ?, store_search_text = ?, interval = ?, filters = ?, \
+                    notifications = ? WHERE id = ?",
+                params![
+                    job.name,
+                    job.url,
+                    job.show_diff,
+                    to_json(&job.mode)?,
+                    to_json(&job.change_detector)?,
+                    to_json(&job.triggers)?,
+                    optional_to_json(&job.confirmation)?,
+                    job.ignore_recent_snapshots,
+                    optional_to_json(&job.retention)?,
+                    job.store_raw,
+                    job.store_search_text,
+                    job.interval as i64,
+                    to_json(&job.filters)?,
+                    to_json(&job.notifications)?,
+                    job.id,
+                ],
+            )?;
+
+            Ok(job)
+        })
+        .await
+    }
+
+    async fn jobs_delete(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+
+        self.with_connection(move |connection| {
+            execute(connection, "DELETE FROM jobs WHERE id = ?", params![id])
+        })
+        .await
+    }
+
+    async fn snapshots_get_all(&self, job_id: &str) -> Result<Vec<Snapshot>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!("{} WHERE job_id = ?", SNAPSHOT_SELECT);
+
+            query_all(connection, sql.as_str(), params![job_id], snapshot_from_row)
+        })
+        .await
+    }
+
+    async fn snapshots_get_latest(&self, job_id: &str) -> Result<Option<Snapshot>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "{} WHERE job_id = ? ORDER BY created_at DESC, snapshots.id DESC LIMIT 1",
+                SNAPSHOT_SELECT
+            );
+
+            query_one(connection, sql.as_str(), params![job_id], snapshot_from_row)
+        })
+        .await
+    }
+
+    async fn snapshots_get_recent(&self, job_id: &str, limit: i64) -> Result<Vec<Snapshot>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "{} WHERE job_id = ? ORDER BY created_at DESC, snapshots.id DESC LIMIT ?",
+                SNAPSHOT_SELECT
+            );
+
+            query_all(
+                connection,
+                sql.as_str(),
+                params![job_id, limit],
+                snapshot_from_row,
+            )
+        })
+        .await
+    }
+
+    async fn snapshots_get_values(&self, job_id: &str) -> Result<Vec<Snapshot>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "{} WHERE job_id = ? AND value IS NOT NULL \
+                ORDER BY created_at ASC, snapshots.id ASC",
+                SNAPSHOT_SELECT
+            );
+
+            query_all(connection, sql.as_str(), params![job_id], snapshot_from_row)
+        })
+        .await
+    }
+
+    async fn snapshots_add(&self, snapshot: InsertableSnapshot) -> Result<Snapshot> {
+        let snapshot = Snapshot {
+            id: ObjectId::new().to_hex(),
+            job_id: snapshot.job_id,
+            data: snapshot.data,
+            value: snapshot.value,
+            raw_data: snapshot.raw_data,
+            created_at: snapshot.created_at,
+            fetch: snapshot.fetch,
+        };
+
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+
+            let body_hash = store_body(&transaction, snapshot.data.as_str())?;
+            index_body(&transaction, body_hash.as_str(), snapshot.data.as_str())?;
+            let raw_body_hash = snapshot
+                .raw_data
+                .as_deref()
+                .map(|raw_data| store_body(&transaction, raw_data))
+                .transpose()?;
+
+            let sql = format!(
+                "INSERT INTO snapshots ({}) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                SNAPSHOT_COLUMNS
+            );
+            transaction.execute(
+                sql.as_str(),
+                params![
+                    snapshot.id,
+                    snapshot.job_id,
+                    body_hash,
+                    raw_body_hash,
+                    snapshot.value,
+                    snapshot.created_at.timestamp_millis(),
+                    optional_to_json(&snapshot.fetch)?,
+                ],
+            )?;
+
+            transaction.commit()?;
+
+            Ok(snapshot)
+        })
+        .await
+    }
+
+    async fn snapshots_get_one(&self, id: &str) -> Result<Option<Snapshot>> {
+        let id = id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!("{} WHERE snapshots.id = ?", SNAPSHOT_SELECT);
+
+            query_one(connection, sql.as_str(), params![id], snapshot_from_row)
+        })
+        .await
+    }
+
+    async fn snapshots_delete(&self, id: &str) -> Result<()> {
+        self.snapshots_delete_many(&[id.to_string()]).await?;
+
+        Ok(())
+    }
+
+    async fn snapshots_delete_many(&self, ids: &[String]) -> Result<PruneReport> {
+        let ids = ids.to_vec();
+
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+
+            let mut deleted = 0;
+            let mut hashes: Vec<String> = Vec::new();
+            for id in ids {
+                let snapshot_hashes = query_one(
+                    &transaction,
+                    "SELECT body_hash, raw_body_hash FROM snapshots WHERE id = ?",
+                    params![id],
+                    |row| {
+                        Ok((
+                            row.get::<_, Option<String>>(0)?,
+                            row.get::<_, Option<String>>(1)?,
+                        ))
+                    },
+                )?;
+
+                if let Some((body_hash, raw_body_hash)) = snapshot_hashes {
+                    transaction.execute("DELETE FROM snapshots WHERE id = ?", params![id])?;
+                    deleted += 1;
+                    hashes.extend(body_hash);
+                    hashes.extend(raw_body_hash);
+                }
+            }
+
+            // The transaction holds the write lock, so no snapshot using the bodies can be added
+            // between checking that they're unused and removing them
+            let mut bytes_removed = 0;
+            for hash in hashes {
+                bytes_removed += remove_unused_body(&transaction, hash.as_str())?;
+            }
+            transaction.commit()?;
+
+            Ok(PruneReport {
+                snapshots_removed: deleted,
+                bytes_removed,
+            })
+        })
+        .await
+    }
+
+    async fn snapshots_query(&self, job_id: &str, query: &SnapshotQuery) -> Result<SnapshotPage> {
+        let job_id = job_id.to_string();
+        let query = query.clone();
+
+        self.with_connection(move |connection| {
+            let (conditions, values) =
+                range_conditions(Some(job_id.as_str()), query.after, query.before);
+
+            query_page(connection, conditions, values, &query)
+        })
+        .await
+    }
+
+    async fn snapshots_search(
+        &self,
+        text: &str,
+        job_id: Option<&str>,
+        query: &SnapshotQuery,
+    ) -> Result<SnapshotPage> {
+        let text = text.to_string();
+        let job_id = job_id.map(str::to_string);
+        let query = query.clone();
+
+        self.with_connection(move |connection| {
+            let (mut conditions, mut values) =
+                range_conditions(job_id.as_deref(), query.after, query.before);
+
+            // Shorter texts aren't in the index, so all snapshots have to be checked for them
+            if text.chars().count() >= TRIGRAM_LENGTH {
+                conditions.push(
+                    "body_hash IN (SELECT hash FROM snapshot_bodies WHERE search_id IN \
+                        (SELECT rowid FROM snapshot_search WHERE snapshot_search MATCH ?))",
+                );
+                values.push(Box::new(format!("\"{}\"", text.replace('"', "\"\""))));
+            }
+
+            query_page(connection, conditions, values, &query)
+        })
+        .await
+    }
+
+    async fn snapshots_count(
+        &self,
+        job_id: &str,
+        after: Option<DateTime>,
+        before: Option<DateTime>,
+    ) -> Result<u64> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let (conditions, values) = range_conditions(Some(job_id.as_str()), after, before);
+            let sql = format!(
+                "SELECT COUNT(*) FROM snapshots WHERE {}",
+                conditions.join(" AND ")
+            );
+
+            let params = values.iter().map(Box::as_ref).collect::<Vec<&dyn ToSql>>();
+            let count = query_one(connection, sql.as_str(), &params, |row| {
+                Ok(row.get::<_, i64>(0)?)
+            })?;
+
+            Ok(count.unwrap_or(0) as u64)
+        })
+        .await
+    }
+
+    async fn snapshots_get_headers(&self, job_id: &str) -> Result<Vec<SnapshotHeader>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            query_all(
+                connection,
+                "SELECT id, created_at FROM snapshots WHERE job_id = ?",
+                params![job_id],
+                |row| {
+                    Ok(SnapshotHeader {
+                        id: row.get(0)?,
+                        created_at: datetime_column(row, 1)?,
+                    })
+                },
+            )
+        })
+        .await
+    }
+
+    async fn snapshots_get_versions_back(
+        &self,
+        job_id: &str,
+        versions: u64,
+    ) -> Result<Option<Snapshot>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "{} WHERE job_id = ? ORDER BY created_at DESC, snapshots.id DESC LIMIT 1 OFFSET ?",
+                SNAPSHOT_SELECT
+            );
+
+            query_one(
+                connection,
+                sql.as_str(),
+                params![job_id, versions as i64],
+                snapshot_from_row,
+            )
+        })
+        .await
+    }
+
+    async fn feed_items_get_all(&self, job_id: &str) -> Result<Vec<FeedItem>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            query_all(
+                connection,
+                "SELECT job_id, item_id FROM feed_items WHERE job_id = ?",
+                params![job_id],
+                |row| {
+                    Ok(FeedItem {
+                        job_id: row.get(0)?,
+                        item_id: row.get(1)?,
+                    })
+                },
+            )
+        })
+        .await
+    }
+
+    async fn feed_items_add(&self, items: Vec<FeedItem>) -> Result<()> {
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+
+            for item in items {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO feed_items (job_id, item_id) VALUES (?, ?)",
+                    params![item.job_id, item.item_id],
+                )?;
+            }
+
+            transaction.commit()?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn check_runs_get_all(&self, job_id: &str) -> Result<Vec<CheckRun>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "SELECT {} FROM check_runs WHERE job_id = ? ORDER BY started_at DESC, id DESC",
+                CHECK_RUN_COLUMNS
+            );
+
+            query_all(
+                connection,
+                sql.as_str(),
+                params![job_id],
+                check_run_from_row,
+            )
+        })
+        .await
+    }
+
+    async fn check_runs_get_latest(&self, job_id: &str) -> Result<Option<CheckRun>> {
+        let job_id = job_id.to_string();
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "SELECT {} FROM check_runs WHERE job_id = ? ORDER BY started_at DESC, id DESC LIMIT 1",
+                CHECK_RUN_COLUMNS
+            );
+
+            query_one(
+                connection,
+                sql.as_str(),
+                params![job_id],
+                check_run_from_row,
+            )
+        })
+        .await
+    }
+
+    async fn check_runs_add(&self, check_run: InsertableCheckRun) -> Result<CheckRun> {
+        let check_run = CheckRun {
+            id: ObjectId::new().to_hex(),
+            job_id: check_run.job_id,
+            started_at: check_run.started_at,
+            finished_at: check_run.finished_at,
+            outcome: check_run.outcome,
+            error: check_run.error,
+            snapshot_id: check_run.snapshot_id,
+        };
+
+        self.with_connection(move |connection| {
+            let sql = format!(
+                "INSERT INTO check_runs ({}) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                CHECK_RUN_COLUMNS
+            );
+            execute(
+                connection,
+                sql.as_str(),
+                params![
+                    check_run.id,
+                    check_run.job_id,
+                    check_run.started_at.timestamp_millis(),
+                    check_run.finished_at.timestamp_millis(),
+                    to_json(&check_run.outcome)?,
+                    check_run.error,
+                    check_run.snapshot_id,
+                ],
+            )?;
+
+            Ok(check_run)
+        })
+        .await
+    }
+}
+
+/// Returns the conditions and their parameters to select the snapshots (of all jobs or only
+/// the given one) created at or after `after` and before `before`
+fn range_conditions(
+    job_id: Option<&str>,
+    after: Option<DateTime>,
+    before: Option<DateTime>,
+) -> (Vec<&'static str>, Vec<Box<dyn ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+    if let Some(job_id) = job_id {
+        conditions.push("job_id = ?");
+        values.push(Box::new(job_id.to_string()));
+    }
+    if let Some(after) = after {
+        conditions.push("created_at >= ?");
+        values.push(Box::new(after.timestamp_millis()));
+    }
+    if let Some(before) = before {
+        conditions.push("created_at < ?");
+        values.push(Box::new(before.timestamp_millis()));
+    }
+
+    (conditions, values)
+}
+
+/// Selects a page of the snapshots matching the conditions,
+/// in the query's order and starting after its cursor
+fn query_page(
+    connection: &Connection,
+    mut conditions: Vec<&'static str>,
+    mut values: Vec<Box<dyn ToSql>>,
+    query: &SnapshotQuery,
+) -> Result<SnapshotPage> {
+    if let Some(cursor) = &query.cursor {
+        let cursor = SnapshotCursor::parse(cursor)?;
+        conditions.push(match query.order {
+            SortOrder::NewestFirst => "(created_at < ? OR (created_at = ? AND snapshots.id < ?))",
+            SortOrder::OldestFirst => "(created_at > ? OR (created_at = ? AND snapshots.id > ?))",
+        });
+        values.push(Box::new(cursor.created_at));
+        values.push(Box::new(cursor.created_at));
+        values.push(Box::new(cursor.id));
+    }
+
+    let direction = match query.order {
+        SortOrder::NewestFirst => "DESC",
+        SortOrder::OldestFirst => "ASC",
+    };
+    let filter = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    let mut sql = format!(
+        "{}{} ORDER BY created_at {}, snapshots.id {}",
+        SNAPSHOT_SELECT, filter, direction, direction
+    );
+    if let Some(limit) = fetch_limit(query.limit) {
+        sql.push_str(" LIMIT ?");
+        values.push(Box::new(limit));
+    }
+
+    let params = values.iter().map(Box::as_ref).collect::<Vec<&dyn ToSql>>();
+    let snapshots = query_all(connection, sql.as_str(), &params, snapshot_from_row)?;
+
+    Ok(page_from_snapshots(snapshots, query.limit))
+}
+
+fn job_from_row(row: &Row) -> Result<Job> {
+    Ok(Job {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        url: row.get(2)?,
+        show_diff: row.get(3)?,
+        mode: json_column(row, 4)?,
+        change_detector: json_column(row, 5)?,
+        triggers: json_column(row, 6)?,
+        confirmation: optional_json_column(row, 7)?,
+        ignore_recent_snapshots: row.get(8)?,
+        retention: optional_json_column(row, 9)?,
+        store_raw: row.get(10)?,
+        store_search_text: row.get(11)?,
+        interval: row.get::<_, i64>(12)? as u64,
+        filters: json_column(row, 13)?,
+        notifications: json_column(row, 14)?,
+    })
+}
+
+fn snapshot_from_row(row: &Row) -> Result<Snapshot> {
+    let compressed: Option<Vec<u8>> = row.get(5)?;
+    let raw_compressed: Option<Vec<u8>> = row.get(7)?;
+
+    let data = row_data(compressed.as_deref(), row.get(6)?)?;
+    let raw_data = row_raw_data(row.get(8)?, raw_compressed.as_deref())?;
+
+    Ok(Snapshot {
+        id: row.get(0)?,
+        job_id: row.get(1)?,
+        data,
+        value: row.get(2)?,
+        raw_data,
+        created_at: datetime_column(row, 3)?,
+        fetch: optional_json_column(row, 4)?,
+    })
+}
+
+fn check_run_from_row(row: &Row) -> Result<CheckRun> {
+    Ok(CheckRun {
+        id: row.get(0)?,
+        job_id: row.get(1)?,
+        started_at: datetime_column(row, 2)?,
+        finished_at: datetime_column(row, 3)?,
+        outcome: json_column(row, 4)?,
+        error: row.get(5)?,
+        snapshot_id: row.get(6)?,
+    })
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+fn optional_to_json<T: Serialize>(value: &Option<T>) -> Result<Option<String>> {
+    value.as_ref().map(to_json).transpose()
+}
+
+fn datetime_column(row: &Row, index: usize) -> Result<DateTime> {
+    let millis: i64 = row.get(index)?;
+
+    Ok(DateTime::from(Utc.timestamp_millis(millis)))
+}
+
+fn json_column<T: DeserializeOwned>(row: &Row, index: usize) -> Result<T> {
+    let text: String = row.get(index)?;
+
+    Ok(serde_json::from_str(text.as_str())?)
+}
+
+fn optional_json_column<T: DeserializeOwned>(row: &Row, index: usize) -> Result<Option<T>> {
+    let text: Option<String> = row.get(index)?;
+
+    Ok(text
+        .map(|text| serde_json::from_str(text.as_str()))
+        .transpose()?)
+}
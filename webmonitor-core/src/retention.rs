@@ -0,0 +1,279 @@
+use std::{collections::HashSet, env, sync::Arc, time::Duration};
+
+use log::{info, warn};
+use mongodb::bson::DateTime;
+use tokio::time;
+
+use crate::{
+    error::Result,
+    model::{PruneReport, RetentionPolicy, SnapshotHeader},
+    repository::Repository,
+};
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+// How often the maintenance task prunes the snapshots of all jobs
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Reads the global retention policy from the RETENTION_KEEP_LAST, RETENTION_KEEP_DAYS
+/// and RETENTION_DAILY_AFTER_DAYS environment variables.
+/// Returns None if none of them are set, meaning snapshots are kept forever.
+pub fn global_policy_from_env() -> Option<RetentionPolicy> {
+    let var = |name: &str| env::var(name).ok().and_then(|value| value.parse().ok());
+
+    let policy = RetentionPolicy {
+        keep_last: var("RETENTION_KEEP_LAST"),
+        keep_days: var("RETENTION_KEEP_DAYS"),
+        daily_after_days: var("RETENTION_DAILY_AFTER_DAYS"),
+    };
+
+    if policy.keep_last.is_none() && policy.keep_days.is_none() && policy.daily_after_days.is_none()
+    {
+        None
+    } else {
+        Some(policy)
+    }
+}
+
+/// Returns the snapshots of a single job that should be removed according to the policy.
+/// The latest snapshot is never returned, as it's needed to detect the next change.
+pub fn snapshots_to_prune<'a>(
+    snapshots: &'a [SnapshotHeader],
+    policy: &RetentionPolicy,
+    now: DateTime,
+) -> Vec<&'a SnapshotHeader> {
+    let mut newest_first: Vec<&SnapshotHeader> = snapshots.iter().collect();
+    newest_first.sort_by(|a, b| {
+        b.created_at
+            .timestamp_millis()
+            .cmp(&a.created_at.timestamp_millis())
+            .then_with(|| b.id.cmp(&a.id))
+    });
+
+    let now = now.timestamp_millis();
+    let mut seen_days = HashSet::new();
+
+    newest_first
+        .into_iter()
+        .enumerate()
+        .filter(|(index, snapshot)| {
+            let created_at = snapshot.created_at.timestamp_millis();
+            let age_days = (now - created_at) / DAY_MILLIS;
+            let first_of_day = seen_days.insert(created_at.div_euclid(DAY_MILLIS));
+
+            if *index == 0 {
+                return false;
+            }
+
+            let beyond_last = policy
+                .keep_last
+                .is_some_and(|keep_last| *index >= keep_last as usize);
+            let expired = policy
+                .keep_days
+                .is_some_and(|keep_days| age_days >= keep_days as i64);
+            let thinned = policy
+                .daily_after_days
+                .is_some_and(|after_days| age_days >= after_days as i64 && !first_of_day);
+
+            beyond_last || expired || thinned
+        })
+        .map(|(_, snapshot)| snapshot)
+        .collect()
+}
+
+/// Prunes the snapshots of every job, using the job's own retention policy
+/// or the global one if the job has none. Jobs without any policy are left alone.
+pub async fn prune_all_jobs(
+    repository: &dyn Repository,
+    global_policy: Option<&RetentionPolicy>,
+) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+
+    for job in repository.jobs_get_all().await? {
+        if let Some(policy) = job.retention.as_ref().or(global_policy) {
+            let job_report = repository.snapshots_prune(&job.id, policy).await?;
+
+            report.snapshots_removed += job_report.snapshots_removed;
+            report.bytes_removed += job_report.bytes_removed;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Spawns a background task that periodically prunes the snapshots of all jobs
+pub fn spawn_maintenance(repository: Arc<dyn Repository>, global_policy: Option<RetentionPolicy>) {
+    tokio::spawn(async move {
+        let mut interval = time::interval(MAINTENANCE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match prune_all_jobs(repository.as_ref(), global_policy.as_ref()).await {
+                Ok(report) if report.snapshots_removed > 0 => {
+                    info!(
+                        "Pruned {} snapshots, freeing {} bytes.",
+                        report.snapshots_removed, report.bytes_removed
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => warn!("There was a problem pruning snapshots: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{
+        model::{ChangeDetector, InsertableJob, InsertableSnapshot, JobMode},
+        repository::MemoryRepository,
+    };
+
+    const NOW: i64 = 100 * DAY_MILLIS + 1000;
+    const STEP_MILLIS: i64 = DAY_MILLIS / 3;
+
+    // A snapshot every 8 hours, from newest to oldest
+    fn headers(count: i64) -> Vec<SnapshotHeader> {
+        (0..count)
+            .map(|step| SnapshotHeader {
+                id: format!("{:03}", count - step),
+                created_at: datetime(NOW - step * STEP_MILLIS),
+            })
+            .collect()
+    }
+
+    fn datetime(millis: i64) -> DateTime {
+        DateTime::from(Utc.timestamp_millis(millis))
+    }
+
+    // Returns how many 8 hour steps old the pruned snapshots are, from newest to oldest
+    fn pruned_steps(snapshots: &[SnapshotHeader], policy: &RetentionPolicy) -> Vec<i64> {
+        let mut steps: Vec<i64> = snapshots_to_prune(snapshots, policy, datetime(NOW))
+            .iter()
+            .map(|snapshot| (NOW - snapshot.created_at.timestamp_millis()) / STEP_MILLIS)
+            .collect();
+        steps.sort();
+
+        steps
+    }
+
+    #[test]
+    fn keeps_everything_without_rules() {
+        assert!(pruned_steps(&headers(40), &RetentionPolicy::default()).is_empty());
+    }
+
+    #[test]
+    fn keeps_the_last_snapshots() {
+        let policy = RetentionPolicy {
+            keep_last: Some(5),
+            ..RetentionPolicy::default()
+        };
+
+        let expected: Vec<i64> = (5..40).collect();
+        assert_eq!(pruned_steps(&headers(40), &policy), expected);
+    }
+
+    #[test]
+    fn removes_snapshots_older_than_the_given_days() {
+        let policy = RetentionPolicy {
+            keep_days: Some(3),
+            ..RetentionPolicy::default()
+        };
+
+        let expected: Vec<i64> = (9..40).collect();
+        assert_eq!(pruned_steps(&headers(40), &policy), expected);
+    }
+
+    #[test]
+    fn keeps_the_last_snapshot_of_each_day_after_the_given_days() {
+        let policy = RetentionPolicy {
+            daily_after_days: Some(7),
+            ..RetentionPolicy::default()
+        };
+
+        // Days start 1 second after the newest snapshot, so the last snapshot of each older day
+        // is 1, 4, 7, ... steps old
+        let expected: Vec<i64> = (21..40).filter(|step| step % 3 != 1).collect();
+        assert_eq!(pruned_steps(&headers(40), &policy), expected);
+    }
+
+    #[test]
+    fn always_keeps_the_latest_snapshot() {
+        let policy = RetentionPolicy {
+            keep_last: Some(0),
+            keep_days: Some(0),
+            ..RetentionPolicy::default()
+        };
+
+        let expected: Vec<i64> = (1..40).collect();
+        assert_eq!(pruned_steps(&headers(40), &policy), expected);
+    }
+
+    #[test]
+    fn sorts_the_snapshots_by_creation_time() {
+        let policy = RetentionPolicy {
+            keep_last: Some(5),
+            ..RetentionPolicy::default()
+        };
+        let mut oldest_first = headers(40);
+        oldest_first.reverse();
+
+        let expected: Vec<i64> = (5..40).collect();
+        assert_eq!(pruned_steps(&oldest_first, &policy), expected);
+    }
+
+    #[tokio::test]
+    async fn prune_all_jobs_reports_the_removed_snapshots_and_bytes() {
+        let repository = MemoryRepository::new();
+        let job = repository
+            .jobs_add(InsertableJob {
+                name: String::from("Blog"),
+                url: String::from("https://example.com"),
+                show_diff: false,
+                mode: JobMode::Website,
+                change_detector: ChangeDetector::ExactDetector,
+                triggers: vec![],
+                confirmation: None,
+                ignore_recent_snapshots: 0,
+                retention: None,
+                store_raw: false,
+                store_search_text: false,
+                interval: 60,
+                filters: vec![],
+                notifications: vec![],
+            })
+            .await
+            .unwrap();
+
+        for step in 0..3 {
+            repository
+                .snapshots_add(InsertableSnapshot {
+                    job_id: job.id.clone(),
+                    data: format!("Post {}", step),
+                    value: None,
+                    raw_data: None,
+                    created_at: datetime(NOW - step * STEP_MILLIS),
+                    fetch: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            ..RetentionPolicy::default()
+        };
+        let report = prune_all_jobs(&repository, Some(&policy)).await.unwrap();
+
+        assert_eq!(report.snapshots_removed, 2);
+        assert_eq!(report.bytes_removed, 12);
+        assert_eq!(
+            repository.snapshots_get_all(&job.id).await.unwrap().len(),
+            1
+        );
+    }
+}
@@ -38,7 +38,7 @@ impl JobScheduler {
                     break;
                 }
 
-                let result = watcher_ref.run_website_check_for_job(&job).await;
+                let result = watcher_ref.run_check_for_job(&job).await;
 
                 if let Err(e) = result {
                     warn!("There was a problem checking job '{}': {}", &job.name, e);
@@ -0,0 +1,191 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    error::Result,
+    model::{SearchHit, SearchQuery, Snapshot, SnapshotQuery, SortOrder},
+    repository::Repository,
+};
+
+// How many snapshots found by the database's text index are loaded at once
+const PAGE_SIZE: u32 = 100;
+
+// How many characters of context are shown before and after a match
+const EXCERPT_CONTEXT_CHARS: usize = 60;
+
+/// Searches the snapshot history of all jobs (or the one given by the query) for the query's text,
+/// returning the matching snapshots of all jobs together from oldest to newest.
+///
+/// The database's text index finds the snapshots that may contain the text,
+/// which are then checked for the exact text in the requested case.
+pub async fn search_snapshots(
+    repository: &dyn Repository,
+    query: &SearchQuery,
+) -> Result<Vec<SearchHit>> {
+    if query.text.is_empty() || query.limit == Some(0) {
+        return Ok(Vec::new());
+    }
+
+    let pattern = RegexBuilder::new(regex::escape(query.text.as_str()).as_str())
+        .case_insensitive(!query.case_sensitive)
+        .build()?;
+    let limit = query.limit.map(|limit| limit as usize);
+
+    let job_names: HashMap<String, String> = repository
+        .jobs_get_all()
+        .await?
+        .into_iter()
+        .map(|job| (job.id, job.name))
+        .collect();
+
+    let mut page_query = SnapshotQuery {
+        after: query.after,
+        before: query.before,
+        limit: Some(PAGE_SIZE),
+        cursor: None,
+        order: SortOrder::OldestFirst,
+    };
+
+    // The snapshots of all jobs come in order of their creation time,
+    // so the first hit of a job is its oldest one and the limit applies to all jobs together
+    let mut hits: Vec<SearchHit> = Vec::new();
+    let mut found_jobs: HashSet<String> = HashSet::new();
+    loop {
+        let page = repository
+            .snapshots_search(&query.text, query.job_id.as_deref(), &page_query)
+            .await?;
+
+        for snapshot in &page.snapshots {
+            if query.first_only && found_jobs.contains(&snapshot.job_id) {
+                continue;
+            }
+            // Snapshots of deleted jobs aren't searched
+            let job_name = match job_names.get(&snapshot.job_id) {
+                Some(job_name) => job_name,
+                None => continue,
+            };
+
+            if let Some(excerpt) = excerpt(snapshot, &pattern) {
+                found_jobs.insert(snapshot.job_id.clone());
+                hits.push(SearchHit {
+                    job_id: snapshot.job_id.clone(),
+                    job_name: job_name.clone(),
+                    snapshot_id: snapshot.id.clone(),
+                    created_at: snapshot.created_at,
+                    excerpt,
+                });
+
+                if limit.is_some_and(|limit| hits.len() >= limit) {
+                    return Ok(hits);
+                }
+            }
+        }
+
+        match page.next_cursor {
+            Some(cursor) => page_query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+
+    Ok(hits)
+}
+
+/// Returns the text around the first match in the snapshot's data on a single line,
+/// with the match wrapped in `**`, or None if the data doesn't match
+fn excerpt(snapshot: &Snapshot, pattern: &Regex) -> Option<String> {
+    let found = pattern.find(snapshot.data.as_str())?;
+    let data = snapshot.data.as_str();
+
+    let start = context_start(data, found.range());
+    let end = context_end(data, found.range());
+
+    let mut excerpt = String::new();
+    if start > 0 {
+        excerpt.push('…');
+    }
+    excerpt.push_str(&data[start..found.start()]);
+    excerpt.push_str("**");
+    excerpt.push_str(found.as_str());
+    excerpt.push_str("**");
+    excerpt.push_str(&data[found.end()..end]);
+    if end < data.len() {
+        excerpt.push('…');
+    }
+
+    Some(excerpt.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+fn context_start(data: &str, found: Range<usize>) -> usize {
+    data[..found.start]
+        .char_indices()
+        .rev()
+        .take(EXCERPT_CONTEXT_CHARS)
+        .last()
+        .map_or(found.start, |(index, _)| index)
+}
+
+fn context_end(data: &str, found: Range<usize>) -> usize {
+    data[found.end..]
+        .char_indices()
+        .nth(EXCERPT_CONTEXT_CHARS)
+        .map_or(data.len(), |(index, _)| found.end + index)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use mongodb::bson::DateTime;
+
+    use super::*;
+
+    fn snapshot(data: &str) -> Snapshot {
+        Snapshot {
+            id: String::from("snapshot"),
+            job_id: String::from("job"),
+            data: data.to_string(),
+            value: None,
+            raw_data: None,
+            created_at: DateTime::from(Utc.timestamp_millis(0)),
+            fetch: None,
+        }
+    }
+
+    fn pattern(text: &str) -> Regex {
+        RegexBuilder::new(regex::escape(text).as_str())
+            .case_insensitive(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn excerpts_mark_the_match_on_a_single_line() {
+        let snapshot = snapshot("Größe: 42\n  Äpfel für Jürgen");
+
+        let excerpt = excerpt(&snapshot, &pattern("äpfel"));
+
+        assert_eq!(excerpt.as_deref(), Some("Größe: 42 **Äpfel** für Jürgen"));
+    }
+
+    #[test]
+    fn excerpts_cut_multibyte_text_at_character_boundaries() {
+        let data = format!("{}Treffer{}", "ü".repeat(100), "é".repeat(100));
+
+        let excerpt = excerpt(&snapshot(&data), &pattern("treffer"));
+
+        let expected = format!(
+            "…{}**Treffer**{}…",
+            "ü".repeat(EXCERPT_CONTEXT_CHARS),
+            "é".repeat(EXCERPT_CONTEXT_CHARS)
+        );
+        assert_eq!(excerpt, Some(expected));
+    }
+
+    #[test]
+    fn excerpts_need_a_match() {
+        assert_eq!(excerpt(&snapshot("Größe: 42"), &pattern("äpfel")), None);
+    }
+}
@@ -0,0 +1,159 @@
+use regex::Regex;
+
+use crate::{
+    error::{Result, WebmonitorError},
+    model::{JobMode, Trigger},
+};
+
+/// Returns whether all of the given triggers are met by the new content,
+/// meaning notifications should be sent for it.
+/// Triggers that only fire on transitions are additionally required
+/// to not have been met by the previous content, if there is any.
+pub fn triggers_met(triggers: &[Trigger], prev: Option<&str>, new: &str) -> Result<bool> {
+    for trigger in triggers {
+        if !condition_met(trigger, new)? {
+            return Ok(false);
+        }
+
+        if let Some(prev) = prev {
+            if transition_only(trigger) && condition_met(trigger, prev)? {
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Checks that the regular expressions of all given triggers compile,
+/// and that feed jobs don't use transition only triggers. The previous content of a feed job
+/// is just the previous batch of new items, so a transition from it means nothing.
+/// Triggers are rejected for file, image and number jobs, whose snapshots don't hold
+/// the text of the content, but a hash, fingerprint or parsed value of it.
+pub fn validate_triggers(triggers: &[Trigger], mode: JobMode) -> Result<()> {
+    let text_mode = matches!(mode, JobMode::Website | JobMode::Feed);
+    if !text_mode && !triggers.is_empty() {
+        return Err(WebmonitorError::UnsupportedTriggerMode);
+    }
+
+    for trigger in triggers {
+        if mode == JobMode::Feed && transition_only(trigger) {
+            return Err(WebmonitorError::TransitionOnlyFeedTrigger);
+        }
+
+        match trigger {
+            Trigger::MatchesTrigger(options) | Trigger::NotMatchesTrigger(options) => {
+                Regex::new(options.pattern.as_str())?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn condition_met(trigger: &Trigger, content: &str) -> Result<bool> {
+    let met = match trigger {
+        Trigger::ContainsTrigger(options) => content.contains(options.text.as_str()),
+        Trigger::NotContainsTrigger(options) => !content.contains(options.text.as_str()),
+        Trigger::MatchesTrigger(options) => Regex::new(options.pattern.as_str())?.is_match(content),
+        Trigger::NotMatchesTrigger(options) => {
+            !Regex::new(options.pattern.as_str())?.is_match(content)
+        }
+    };
+
+    Ok(met)
+}
+
+fn transition_only(trigger: &Trigger) -> bool {
+    match trigger {
+        Trigger::ContainsTrigger(options) | Trigger::NotContainsTrigger(options) => {
+            options.transition_only
+        }
+        Trigger::MatchesTrigger(options) | Trigger::NotMatchesTrigger(options) => {
+            options.transition_only
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{NumberModeOptions, NumberTrigger, RegexTriggerOptions, TextTriggerOptions};
+
+    fn contains(text: &str, transition_only: bool) -> Trigger {
+        Trigger::ContainsTrigger(TextTriggerOptions {
+            text: text.to_string(),
+            transition_only,
+        })
+    }
+
+    fn not_matches(pattern: &str) -> Trigger {
+        Trigger::NotMatchesTrigger(RegexTriggerOptions {
+            pattern: pattern.to_string(),
+            transition_only: false,
+        })
+    }
+
+    #[test]
+    fn met_without_triggers() {
+        assert!(triggers_met(&[], None, "anything").unwrap());
+    }
+
+    #[test]
+    fn all_triggers_have_to_be_met() {
+        let triggers = [contains("in stock", false), not_matches(r"\d+ left")];
+
+        assert!(triggers_met(&triggers, None, "in stock").unwrap());
+        assert!(!triggers_met(&triggers, None, "in stock, 3 left").unwrap());
+        assert!(!triggers_met(&triggers, None, "sold out").unwrap());
+    }
+
+    #[test]
+    fn transition_only_triggers_need_a_change_of_the_condition() {
+        let triggers = [contains("in stock", true)];
+
+        assert!(triggers_met(&triggers, Some("sold out"), "in stock").unwrap());
+        assert!(!triggers_met(&triggers, Some("in stock"), "still in stock").unwrap());
+        assert!(triggers_met(&triggers, None, "in stock").unwrap());
+    }
+
+    #[test]
+    fn other_triggers_ignore_the_previous_content() {
+        let triggers = [contains("in stock", false)];
+
+        assert!(triggers_met(&triggers, Some("in stock"), "still in stock").unwrap());
+    }
+
+    #[test]
+    fn invalid_patterns_fail() {
+        assert!(validate_triggers(&[not_matches("(")], JobMode::Website).is_err());
+        assert!(triggers_met(&[not_matches("(")], None, "content").is_err());
+    }
+
+    #[test]
+    fn feed_jobs_reject_transition_only_triggers() {
+        assert!(validate_triggers(&[contains("sale", false)], JobMode::Feed).is_ok());
+        assert!(matches!(
+            validate_triggers(&[contains("sale", true)], JobMode::Feed),
+            Err(WebmonitorError::TransitionOnlyFeedTrigger)
+        ));
+        assert!(validate_triggers(&[contains("sale", true)], JobMode::Website).is_ok());
+    }
+
+    #[test]
+    fn only_website_and_feed_jobs_accept_triggers() {
+        let number = JobMode::Number(NumberModeOptions {
+            decimal_separator: None,
+            trigger: NumberTrigger::AnyChange,
+        });
+
+        for mode in &[JobMode::File, number] {
+            assert!(validate_triggers(&[], *mode).is_ok());
+            assert!(matches!(
+                validate_triggers(&[contains("sale", false)], *mode),
+                Err(WebmonitorError::UnsupportedTriggerMode)
+            ));
+        }
+    }
+}
@@ -1,7 +1,10 @@
-use std::error::Error;
+use std::{error::Error, future};
 
 use webmonitor_core::{
-    model::{CSSFilterOptions, DiscordNotificationOptions, Filter, InsertableJob, Notification},
+    model::{
+        CSSFilterOptions, ChangeDetector, DiscordNotificationOptions, Filter, InsertableJob,
+        JobMode, Notification,
+    },
     Webmonitor,
 };
 
@@ -19,10 +22,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
         url: String::from("https://www.unixtimestamp.com/"),
         interval: 10,
         show_diff: true,
+        mode: JobMode::Website,
+        change_detector: ChangeDetector::ExactDetector,
+        triggers: vec![],
+        confirmation: None,
+        ignore_recent_snapshots: 0,
+        retention: None,
+        store_raw: false,
+        store_search_text: false,
 
         filters: vec![
             Filter::CSSFilter(CSSFilterOptions {
                 selector: String::from("div.ui.statistic"),
+                ..Default::default()
             }),
         ],
         notifications: vec![Notification::Discord(DiscordNotificationOptions {
@@ -34,7 +46,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let added_job = &monitor.add_job(job).await?;
     let _ = &monitor.get_job(added_job.id.as_str()).await?;
 
-    loop {}
+    future::pending::<()>().await;
 
     Ok(())
 }